@@ -0,0 +1,37 @@
+use std::process::Command;
+
+fn main() {
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_string))
+        .map(|feature| feature.to_lowercase().replace('_', "-"))
+        .filter(|feature| feature != "default")
+        .collect();
+    // With all providers built, listing each one adds nothing
+    if features.iter().any(|feature| feature == "providers") {
+        features.retain(|feature| !feature.starts_with("provider-"));
+    }
+    features.sort();
+    let features = if features.is_empty() {
+        "none".to_string()
+    } else {
+        features.join(", ")
+    };
+
+    println!("cargo:rustc-env=GIT_HASH={}", git_hash);
+    println!(
+        "cargo:rustc-env=BUILD_DATE={}",
+        chrono::Utc::now().format("%Y-%m-%d")
+    );
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+}
@@ -0,0 +1,145 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditConfig {
+    pub path: String,
+    #[serde(default = "default_max_size")]
+    pub max_size: u64,
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+fn default_max_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+pub struct AuditEntry<'a> {
+    pub host: &'a str,
+    pub provider: &'a str,
+    pub old_ip: Option<&'a str>,
+    pub new_ip: &'a str,
+    pub outcome: &'a str,
+    pub response: &'a str,
+}
+
+// Every record carries the hash of the line before it, so edited or deleted
+// records break the chain. The chain continues across rotated files.
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    timestamp: String,
+    host: &'a str,
+    provider: &'a str,
+    old_ip: Option<&'a str>,
+    new_ip: &'a str,
+    outcome: &'a str,
+    response: &'a str,
+    prev_hash: String,
+}
+
+pub struct AuditLog {
+    // Path of the current log and hash of its last record
+    last: Mutex<Option<(PathBuf, String)>>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self {
+            last: Mutex::new(None),
+        }
+    }
+
+    pub async fn record(&self, config: &AuditConfig, entry: AuditEntry<'_>) -> std::io::Result<()> {
+        let mut last = self.last.lock().await;
+        let path = PathBuf::from(&config.path);
+
+        let prev_hash = match last.as_ref() {
+            Some((last_path, hash)) if *last_path == path => hash.clone(),
+            _ => last_hash(&path).await,
+        };
+
+        rotate_if_needed(&path, config).await?;
+
+        let record = AuditRecord {
+            timestamp: Local::now().to_rfc3339(),
+            host: entry.host,
+            provider: entry.provider,
+            old_ip: entry.old_ip,
+            new_ip: entry.new_ip,
+            outcome: entry.outcome,
+            response: entry.response,
+            prev_hash,
+        };
+        let line = serde_json::to_string(&record)?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        file.write_all(format!("{}\n", line).as_bytes()).await?;
+        file.flush().await?;
+
+        *last = Some((path, hash(&line)));
+        Ok(())
+    }
+}
+
+fn hash(line: &str) -> String {
+    Sha256::digest(line.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", index));
+    path.with_file_name(name)
+}
+
+// Hash of the newest record, looking at the most recent rotated file when the
+// current one is empty. An empty string starts a new chain.
+async fn last_hash(path: &Path) -> String {
+    for candidate in [path.to_path_buf(), rotated_path(path, 1)] {
+        if let Ok(contents) = fs::read_to_string(&candidate).await {
+            if let Some(line) = contents.lines().rev().find(|line| !line.trim().is_empty()) {
+                return hash(line);
+            }
+        }
+    }
+    String::new()
+}
+
+async fn rotate_if_needed(path: &Path, config: &AuditConfig) -> std::io::Result<()> {
+    let size = match fs::metadata(path).await {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Ok(()),
+    };
+    if size < config.max_size {
+        return Ok(());
+    }
+
+    if config.max_files == 0 {
+        return fs::remove_file(path).await;
+    }
+
+    fs::remove_file(rotated_path(path, config.max_files))
+        .await
+        .ok();
+    for index in (1..config.max_files).rev() {
+        fs::rename(rotated_path(path, index), rotated_path(path, index + 1))
+            .await
+            .ok();
+    }
+    fs::rename(path, rotated_path(path, 1)).await
+}
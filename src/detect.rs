@@ -0,0 +1,1361 @@
+use crate::providers::xml_element;
+use crate::{dns, IpFamily};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+
+const STUN_TIMEOUT: Duration = Duration::from_secs(5);
+const STUN_MAGIC_COOKIE: [u8; 4] = [0x21, 0x12, 0xa4, 0x42];
+const ROUTER_TIMEOUT: Duration = Duration::from_secs(3);
+const NATPMP_PORT: u16 = 5351;
+const SSDP: &str = "239.255.255.250:1900";
+const IGD: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const SNMP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How the public address of each family is found. Both default to the HTTP
+// echo service.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IpSourceConfig {
+    #[serde(default)]
+    pub ipv4: IpSource,
+    #[serde(default)]
+    pub ipv6: IpSource,
+}
+
+impl IpSourceConfig {
+    pub fn source(&self, family: IpFamily) -> &IpSource {
+        match family {
+            IpFamily::V4 => &self.ipv4,
+            IpFamily::V6 => &self.ipv6,
+        }
+    }
+
+    // Files read by file sources, which are watched for changes
+    pub fn files(&self) -> Vec<PathBuf> {
+        IpFamily::ALL
+            .into_iter()
+            .flat_map(|family| self.source(family).files())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum IpSource {
+    // An echo service that answers with the address as plain text; ipify
+    // unless another URL is given
+    Http {
+        #[serde(default)]
+        url: Option<String>,
+    },
+    // A resolver that answers with the address the query came from
+    Dns {
+        #[serde(default)]
+        resolver: DnsResolver,
+    },
+    // The mapped address of a STUN binding request
+    Stun {
+        #[serde(default = "default_stun_server")]
+        server: String,
+    },
+    // The global IPv6 address already assigned to a local interface
+    Interface {
+        interface: String,
+    },
+    // The WAN address the local router reports, over NAT-PMP or UPnP IGD
+    Router {
+        #[serde(default)]
+        protocol: RouterProtocol,
+        // Defaults to the default route's gateway (NAT-PMP) or whichever
+        // router answers the UPnP search
+        #[serde(default)]
+        gateway: Option<IpAddr>,
+    },
+    // An interface address read from a MikroTik router's REST API
+    Mikrotik(MikrotikSource),
+    // An SNMP v2c GET of an OID that holds the WAN address
+    Snmp {
+        // Address (and port, default 161) of the gateway
+        host: String,
+        #[serde(default = "default_snmp_community")]
+        community: String,
+        oid: String,
+    },
+    // A shell command that prints the address
+    Command {
+        command: String,
+        // Seconds before the command is killed
+        #[serde(default = "default_command_timeout")]
+        timeout: u64,
+    },
+    // A file other tools (pppd ip-up scripts, exporters) write the address to
+    File {
+        path: String,
+    },
+    // Several sources that have to report the same address
+    Consensus {
+        sources: Vec<IpSource>,
+        // Defaults to a majority of the sources
+        #[serde(default)]
+        required: Option<usize>,
+    },
+    // Several sources asked at once; the first address `agree` of them report
+    // wins
+    Race {
+        sources: Vec<IpSource>,
+        #[serde(default = "default_race_agree")]
+        agree: usize,
+    },
+}
+
+impl Default for IpSource {
+    fn default() -> Self {
+        IpSource::Http { url: None }
+    }
+}
+
+fn default_race_agree() -> usize {
+    1
+}
+
+fn default_command_timeout() -> u64 {
+    10
+}
+
+fn default_snmp_community() -> String {
+    "public".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MikrotikSource {
+    // Host (and port) of the router; HTTPS unless given with `http://`
+    pub address: String,
+    pub user: String,
+    #[serde(default)]
+    pub pass: String,
+    pub interface: String,
+    // Accept the router's self-signed certificate
+    #[serde(default)]
+    pub insecure: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RouterProtocol {
+    // NAT-PMP first, UPnP if the router does not speak it
+    #[default]
+    Auto,
+    NatPmp,
+    Upnp,
+}
+
+fn default_stun_server() -> String {
+    "stun.l.google.com:19302".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsResolver {
+    // A/AAAA myip.opendns.com at resolver1.opendns.com
+    #[default]
+    OpenDns,
+    // TXT whoami.cloudflare (class CH) at 1.1.1.1
+    Cloudflare,
+}
+
+impl IpSource {
+    // Whether the source asks a service on the internet, so a failed
+    // connectivity check means it cannot work either
+    pub fn uses_internet(&self) -> bool {
+        match self {
+            IpSource::Http { .. } | IpSource::Dns { .. } | IpSource::Stun { .. } => true,
+            IpSource::Consensus { sources, .. } | IpSource::Race { sources, .. } => {
+                sources.iter().all(IpSource::uses_internet)
+            }
+            _ => false,
+        }
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        match self {
+            IpSource::File { path } => vec![PathBuf::from(path)],
+            IpSource::Consensus { sources, .. } | IpSource::Race { sources, .. } => {
+                sources.iter().flat_map(IpSource::files).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+// Whether the address is reachable from the internet: not private, CGNAT
+// (100.64.0.0/10), loopback, link-local, ULA, documentation or otherwise
+// reserved
+pub fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || a == 100 && (64..128).contains(&b)
+                || a == 192 && b == 0 && c == 0
+                || a == 198 && (18..20).contains(&b))
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            // Global unicast is 2000::/3, which leaves out ULA, link-local and
+            // IPv4-mapped addresses
+            first & 0xe000 == 0x2000 && !(first == 0x2001 && ip.segments()[1] == 0x0db8)
+        }
+    }
+}
+
+pub async fn detect(
+    source: &IpSource,
+    client: &reqwest::Client,
+    family: IpFamily,
+) -> Result<IpAddr, String> {
+    match source {
+        IpSource::Consensus { sources, required } => {
+            from_consensus(sources, *required, client, family).await
+        }
+        IpSource::Race { sources, agree } => from_race(sources, *agree, client, family).await,
+        source => detect_one(source, client, family).await,
+    }
+}
+
+// Asks every source and takes the address enough of them agree on, so a single
+// broken or hijacked source cannot steer the record somewhere else
+async fn from_consensus(
+    sources: &[IpSource],
+    required: Option<usize>,
+    client: &reqwest::Client,
+    family: IpFamily,
+) -> Result<IpAddr, String> {
+    let required = required.unwrap_or(sources.len() / 2 + 1).max(1);
+    let mut votes: Vec<(IpAddr, usize)> = Vec::new();
+    let mut failed = 0;
+    for (i, source) in sources.iter().enumerate() {
+        match detect_one(source, client, family).await {
+            Ok(ip) => match votes.iter_mut().find(|(voted, _)| *voted == ip) {
+                Some((_, count)) => *count += 1,
+                None => votes.push((ip, 1)),
+            },
+            Err(e) => {
+                warn!("⚠ Consensus source {} failed: {}", i + 1, e);
+                failed += 1;
+            }
+        }
+    }
+
+    votes.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    let results = votes
+        .iter()
+        .map(|(ip, count)| format!("{} ({})", ip, count))
+        .chain((failed > 0).then(|| format!("{} failed", failed)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match votes.as_slice() {
+        [(ip, count), rest @ ..] if *count >= required => {
+            if rest.first().is_some_and(|(_, count)| *count >= required) {
+                return Err(format!("sources disagree: {}", results));
+            }
+            Ok(*ip)
+        }
+        _ => Err(format!(
+            "no {} of {} sources agree: {}",
+            required,
+            sources.len(),
+            if results.is_empty() { "none" } else { &results }
+        )),
+    }
+}
+
+// Asks all sources at once and returns as soon as enough agree, so a slow or
+// unreachable source does not hold up detection. The rest are cancelled.
+async fn from_race(
+    sources: &[IpSource],
+    agree: usize,
+    client: &reqwest::Client,
+    family: IpFamily,
+) -> Result<IpAddr, String> {
+    let agree = agree.max(1);
+    let mut pending = tokio::task::JoinSet::new();
+    for source in sources {
+        let (source, client) = (source.clone(), client.clone());
+        pending.spawn(async move { detect_one(&source, &client, family).await });
+    }
+
+    let mut votes: Vec<(IpAddr, usize)> = Vec::new();
+    let mut errors = Vec::new();
+    while let Some(result) = pending.join_next().await {
+        match result {
+            Ok(Ok(ip)) => {
+                let count = match votes.iter_mut().find(|(voted, _)| *voted == ip) {
+                    Some((_, count)) => {
+                        *count += 1;
+                        *count
+                    }
+                    None => {
+                        votes.push((ip, 1));
+                        1
+                    }
+                };
+                if count >= agree {
+                    return Ok(ip);
+                }
+            }
+            Ok(Err(e)) => errors.push(e),
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    if votes.is_empty() {
+        return Err(format!("all sources failed: {}", errors.join("; ")));
+    }
+    Err(format!(
+        "no {} sources agree: {}",
+        agree,
+        votes
+            .iter()
+            .map(|(ip, count)| format!("{} ({})", ip, count))
+            .chain(errors.iter().map(|e| format!("failed: {}", e)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+async fn detect_one(
+    source: &IpSource,
+    client: &reqwest::Client,
+    family: IpFamily,
+) -> Result<IpAddr, String> {
+    let ip = match source {
+        IpSource::Http { url } => from_http(client, url.as_deref(), family).await?,
+        IpSource::Dns { resolver } => from_dns(*resolver, family).await?,
+        IpSource::Stun { server } => from_stun(server, family).await?,
+        IpSource::Interface { interface } => from_interface(interface, family).await?,
+        IpSource::Router { protocol, gateway } => {
+            from_router(client, *protocol, *gateway, family).await?
+        }
+        IpSource::Mikrotik(mikrotik) => from_mikrotik(client, mikrotik, family).await?,
+        IpSource::Snmp {
+            host,
+            community,
+            oid,
+        } => from_snmp(host, community, oid).await?,
+        IpSource::Command { command, timeout } => from_command(command, *timeout).await?,
+        IpSource::File { path } => {
+            let text = tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| format!("cannot read {}: {}", path, e))?;
+            first_address(&text).map_err(|e| format!("{} holds {}", path, e))?
+        }
+        IpSource::Consensus { .. } | IpSource::Race { .. } => {
+            return Err("consensus and race sources cannot be nested".to_string());
+        }
+    };
+    if IpFamily::of(&ip) != family {
+        return Err(format!(
+            "unexpected {} address: '{}'",
+            family.record_type(),
+            ip
+        ));
+    }
+    Ok(ip)
+}
+
+async fn from_http(
+    client: &reqwest::Client,
+    url: Option<&str>,
+    family: IpFamily,
+) -> Result<IpAddr, String> {
+    let url = url.unwrap_or(match family {
+        IpFamily::V4 => "https://api.ipify.org",
+        IpFamily::V6 => "https://api6.ipify.org",
+    });
+    let resp = client.get(url).send().await.map_err(|e| {
+        if e.is_timeout() {
+            "timeout - check internet connection".to_string()
+        } else if e.is_connect() {
+            "connection failed - check internet connection".to_string()
+        } else {
+            format!("network error: {}", e)
+        }
+    })?;
+
+    if !resp.status().is_success() {
+        return Err(format!("API returned status: {}", resp.status()));
+    }
+
+    let ip = resp
+        .text()
+        .await
+        .map_err(|e| format!("network error: {}", e))?
+        .trim()
+        .to_string();
+    ip.parse()
+        .map_err(|_| format!("unexpected {} address: '{}'", family.record_type(), ip))
+}
+
+async fn from_dns(resolver: DnsResolver, family: IpFamily) -> Result<IpAddr, String> {
+    // Record type and class of the question
+    let (server, name, qtype, qclass): (IpAddr, _, u16, u16) = match (resolver, family) {
+        (DnsResolver::OpenDns, IpFamily::V4) => {
+            ([208, 67, 222, 222].into(), "myip.opendns.com", 1, 1)
+        }
+        (DnsResolver::OpenDns, IpFamily::V6) => (
+            [0x2620, 0x119, 0x35, 0, 0, 0, 0, 0x35].into(),
+            "myip.opendns.com",
+            28,
+            1,
+        ),
+        (DnsResolver::Cloudflare, IpFamily::V4) => {
+            ([1, 1, 1, 1].into(), "whoami.cloudflare", 16, 3)
+        }
+        (DnsResolver::Cloudflare, IpFamily::V6) => (
+            [0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111].into(),
+            "whoami.cloudflare",
+            16,
+            3,
+        ),
+    };
+    let server = SocketAddr::new(server, 53);
+
+    let msg = dns::query_class(server, name, qtype, qclass, true).await?;
+    parse_answer(&msg, qtype)
+        .ok_or_else(|| format!("no usable answer from {} for {}", server, name))
+}
+
+fn parse_answer(msg: &[u8], qtype: u16) -> Option<IpAddr> {
+    let answer = dns::answers(msg)?
+        .into_iter()
+        .find(|answer| answer.rtype == qtype)?;
+    let rdata = &msg[answer.rdata];
+    match qtype {
+        1 => <[u8; 4]>::try_from(rdata).ok().map(IpAddr::from),
+        28 => <[u8; 16]>::try_from(rdata).ok().map(IpAddr::from),
+        // TXT: the address is the first character-string
+        _ => {
+            let len = *rdata.first()? as usize;
+            std::str::from_utf8(rdata.get(1..1 + len)?)
+                .ok()?
+                .parse()
+                .ok()
+        }
+    }
+}
+
+// Picks the interface's stable global unicast IPv6 address from the kernel's
+// address list. Temporary (privacy extension) addresses change several times a
+// day, and deprecated or tentative ones are about to go or not usable yet.
+async fn from_interface(interface: &str, family: IpFamily) -> Result<IpAddr, String> {
+    const TEMPORARY: u8 = 0x01;
+    const DAD_FAILED: u8 = 0x08;
+    const DEPRECATED: u8 = 0x20;
+    const TENTATIVE: u8 = 0x40;
+
+    if family != IpFamily::V6 {
+        return Err("the interface source only detects IPv6 addresses".to_string());
+    }
+    let list = tokio::fs::read_to_string("/proc/net/if_inet6")
+        .await
+        .map_err(|e| format!("cannot read interface addresses: {}", e))?;
+
+    // address, index, prefix length, scope, flags, interface name
+    let mut found = false;
+    for line in list.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [address, _, _, scope, flags, name] = fields[..] else {
+            continue;
+        };
+        if name != interface {
+            continue;
+        }
+        found = true;
+        let (Ok(address), Ok(flags)) = (
+            u128::from_str_radix(address, 16),
+            u8::from_str_radix(flags, 16),
+        ) else {
+            continue;
+        };
+        let ip = std::net::Ipv6Addr::from(address);
+        // Global scope and 2000::/3, which leaves out ULAs
+        if scope != "00"
+            || ip.segments()[0] & 0xe000 != 0x2000
+            || flags & (TEMPORARY | DAD_FAILED | DEPRECATED | TENTATIVE) != 0
+        {
+            continue;
+        }
+        return Ok(IpAddr::V6(ip));
+    }
+
+    if found {
+        Err(format!(
+            "interface {} has no stable global IPv6 address",
+            interface
+        ))
+    } else {
+        Err(format!(
+            "interface {} not found or has no IPv6 addresses",
+            interface
+        ))
+    }
+}
+
+async fn from_router(
+    client: &reqwest::Client,
+    protocol: RouterProtocol,
+    gateway: Option<IpAddr>,
+    family: IpFamily,
+) -> Result<IpAddr, String> {
+    if family != IpFamily::V4 {
+        return Err("the router source only detects IPv4 addresses".to_string());
+    }
+    let gateway = match gateway {
+        Some(IpAddr::V4(gateway)) => Some(gateway),
+        Some(IpAddr::V6(_)) => return Err("router gateway must be an IPv4 address".to_string()),
+        None => None,
+    };
+
+    let ip = match protocol {
+        RouterProtocol::NatPmp => from_natpmp(gateway).await?,
+        RouterProtocol::Upnp => from_upnp(client, gateway).await?,
+        RouterProtocol::Auto => match from_natpmp(gateway).await {
+            Ok(ip) => ip,
+            Err(natpmp) => from_upnp(client, gateway)
+                .await
+                .map_err(|upnp| format!("NAT-PMP: {}; UPnP: {}", natpmp, upnp))?,
+        },
+    };
+
+    // Behind another NAT the WAN address is not the public one; publish_to
+    // applies private_ips to it like to any detected address
+    Ok(IpAddr::V4(ip))
+}
+
+// NAT-PMP (RFC 6886) external address request to the gateway
+async fn from_natpmp(gateway: Option<Ipv4Addr>) -> Result<Ipv4Addr, String> {
+    let gateway = match gateway {
+        Some(gateway) => gateway,
+        None => default_gateway().await?,
+    };
+    let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], 0)))
+        .await
+        .map_err(|e| format!("cannot open UDP socket: {}", e))?;
+    socket
+        .connect((gateway, NATPMP_PORT))
+        .await
+        .map_err(|e| format!("cannot reach gateway {}: {}", gateway, e))?;
+    // Version 0, opcode 0: external address request
+    socket
+        .send(&[0, 0])
+        .await
+        .map_err(|e| format!("failed to send NAT-PMP request to {}: {}", gateway, e))?;
+
+    let mut buf = [0u8; 16];
+    let len = tokio::time::timeout(ROUTER_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .map_err(|_| format!("no NAT-PMP answer from {}", gateway))?
+        .map_err(|e| format!("no NAT-PMP answer from {}: {}", gateway, e))?;
+    if len < 12 || buf[0] != 0 || buf[1] != 128 {
+        return Err(format!("unexpected NAT-PMP answer from {}", gateway));
+    }
+    match u16::from_be_bytes([buf[2], buf[3]]) {
+        0 => Ok(Ipv4Addr::new(buf[8], buf[9], buf[10], buf[11])),
+        code => Err(format!(
+            "NAT-PMP request refused by {} (result {})",
+            gateway, code
+        )),
+    }
+}
+
+// Gateway of the IPv4 default route, from the kernel's routing table
+async fn default_gateway() -> Result<Ipv4Addr, String> {
+    let routes = tokio::fs::read_to_string("/proc/net/route")
+        .await
+        .map_err(|e| format!("cannot read the routing table ({}) - set gateway", e))?;
+    // Interface, destination, gateway, ... with addresses in little-endian hex
+    routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                [_, "00000000", gateway, ..] => u32::from_str_radix(gateway, 16).ok(),
+                _ => None,
+            }
+        })
+        .find(|gateway| *gateway != 0)
+        .map(|gateway| Ipv4Addr::from(gateway.to_le_bytes()))
+        .ok_or_else(|| "no default route - set gateway".to_string())
+}
+
+// Finds the router with an SSDP search and calls GetExternalIPAddress on its
+// WAN connection service
+async fn from_upnp(
+    client: &reqwest::Client,
+    gateway: Option<Ipv4Addr>,
+) -> Result<Ipv4Addr, String> {
+    let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], 0)))
+        .await
+        .map_err(|e| format!("cannot open UDP socket: {}", e))?;
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+        SSDP, IGD
+    );
+    // A configured gateway is asked directly instead of the multicast group
+    let target = match gateway {
+        Some(gateway) => SocketAddr::from((gateway, 1900)),
+        None => SSDP.parse().expect("valid SSDP address"),
+    };
+    socket
+        .send_to(search.as_bytes(), target)
+        .await
+        .map_err(|e| format!("failed to send UPnP search: {}", e))?;
+
+    let mut buf = [0u8; 2048];
+    let deadline = tokio::time::Instant::now() + ROUTER_TIMEOUT;
+    let location = loop {
+        let len = tokio::time::timeout_at(deadline, socket.recv(&mut buf))
+            .await
+            .map_err(|_| "no UPnP gateway answered".to_string())?
+            .map_err(|e| format!("failed to read UPnP answer: {}", e))?;
+        let answer = String::from_utf8_lossy(&buf[..len]);
+        let location = answer.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("location")
+                .then(|| value.trim().to_string())
+        });
+        if let Some(location) = location {
+            break location;
+        }
+    };
+
+    let description = client
+        .get(&location)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("cannot read the UPnP description at {}: {}", location, e))?
+        .text()
+        .await
+        .map_err(|e| format!("cannot read the UPnP description at {}: {}", location, e))?;
+    let (service, control_url) = description
+        .split("<service>")
+        .skip(1)
+        .filter_map(|service| {
+            Some((
+                xml_element(service, "serviceType")?,
+                xml_element(service, "controlURL")?,
+            ))
+        })
+        .find(|(service, _)| {
+            service.starts_with("urn:schemas-upnp-org:service:WANIPConnection:")
+                || service.starts_with("urn:schemas-upnp-org:service:WANPPPConnection:")
+        })
+        .ok_or_else(|| "the UPnP gateway has no WAN connection service".to_string())?;
+    let control_url = if control_url.starts_with("http") {
+        control_url
+    } else {
+        // Relative to the origin of the description
+        let origin = location
+            .find("://")
+            .and_then(|scheme| location[scheme + 3..].find('/').map(|i| scheme + 3 + i))
+            .map_or(location.as_str(), |end| &location[..end]);
+        format!("{}/{}", origin, control_url.trim_start_matches('/'))
+    };
+
+    let body = format!(
+        concat!(
+            r#"<?xml version="1.0"?>"#,
+            r#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/" "#,
+            r#"s:encodingStyle="http://schemas.xmlsoap.org/soap/encoding/">"#,
+            r#"<s:Body><u:GetExternalIPAddress xmlns:u="{}"/></s:Body></s:Envelope>"#
+        ),
+        service
+    );
+    let answer = client
+        .post(&control_url)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            r#"text/xml; charset="utf-8""#,
+        )
+        .header(
+            "SOAPAction",
+            format!(r#""{}#GetExternalIPAddress""#, service),
+        )
+        .body(body)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("GetExternalIPAddress failed: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("GetExternalIPAddress failed: {}", e))?;
+
+    let ip = xml_element(&answer, "NewExternalIPAddress").unwrap_or_default();
+    ip.parse()
+        .map_err(|_| format!("UPnP gateway reported no WAN address ('{}')", ip))
+}
+
+// Reads the addresses of the interface through RouterOS's REST API (v7) and
+// returns the first enabled global one
+async fn from_mikrotik(
+    client: &reqwest::Client,
+    mikrotik: &MikrotikSource,
+    family: IpFamily,
+) -> Result<IpAddr, String> {
+    let base =
+        if mikrotik.address.starts_with("http://") || mikrotik.address.starts_with("https://") {
+            mikrotik.address.trim_end_matches('/').to_string()
+        } else {
+            format!("https://{}", mikrotik.address)
+        };
+    let path = match family {
+        IpFamily::V4 => "ip/address",
+        IpFamily::V6 => "ipv6/address",
+    };
+
+    let insecure_client;
+    let client = if mikrotik.insecure {
+        insecure_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .danger_accept_invalid_certs(true)
+            .build()
+            .map_err(|e| format!("cannot build HTTP client: {}", e))?;
+        &insecure_client
+    } else {
+        client
+    };
+    let resp = client
+        .get(format!("{}/rest/{}", base, path))
+        .query(&[("interface", mikrotik.interface.as_str())])
+        .basic_auth(&mikrotik.user, Some(&mikrotik.pass))
+        .send()
+        .await
+        .map_err(|e| format!("cannot reach the router at {}: {}", base, e))?;
+    let status = resp.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err("the router rejected the user and password".to_string());
+    }
+    if !status.is_success() {
+        return Err(format!("router returned status: {}", status));
+    }
+    let addresses: Vec<serde_json::Value> = resp
+        .json()
+        .await
+        .map_err(|e| format!("unexpected answer from the router: {}", e))?;
+
+    // Addresses come as `203.0.113.5/24`; flags are the strings "true"/"false"
+    addresses
+        .iter()
+        .filter(|a| a["disabled"] != "true" && a["invalid"] != "true")
+        .filter_map(|a| a["address"].as_str()?.split('/').next()?.parse().ok())
+        .find(|ip: &IpAddr| match ip {
+            IpAddr::V4(ip) => !ip.is_loopback() && !ip.is_link_local(),
+            IpAddr::V6(ip) => ip.segments()[0] & 0xe000 == 0x2000,
+        })
+        .ok_or_else(|| {
+            format!(
+                "interface {} has no {} address on the router",
+                mikrotik.interface,
+                family.record_type()
+            )
+        })
+}
+
+// Runs the command through `sh -c` and takes the first line it prints
+async fn from_command(command: &str, timeout: u64) -> Result<IpAddr, String> {
+    let child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(Duration::from_secs(timeout), child)
+        .await
+        .map_err(|_| format!("command timed out after {}s", timeout))?
+        .map_err(|e| format!("cannot run command: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => format!("command failed with {}", output.status),
+            stderr => format!("command failed with {}: {}", output.status, stderr),
+        });
+    }
+
+    first_address(&String::from_utf8_lossy(&output.stdout))
+        .map_err(|e| format!("command printed {}", e))
+}
+
+// Parses the first non-empty line. A trailing prefix length (`2001:db8::1/64`)
+// is dropped, so `ip addr` output cut down to the address column works as is.
+fn first_address(text: &str) -> Result<IpAddr, String> {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .ok_or("no address")?;
+    let address = line.split('/').next().unwrap_or(line);
+    address
+        .parse()
+        .map_err(|_| format!("an invalid address: '{}'", line))
+}
+
+// SNMP v2c GET of a single OID. The value may be an IpAddress, or an octet
+// string with the address as text or as 4/16 raw bytes.
+async fn from_snmp(host: &str, community: &str, oid: &str) -> Result<IpAddr, String> {
+    let oid = encode_oid(oid).ok_or_else(|| format!("invalid SNMP OID '{}'", oid))?;
+    let target = match host.parse::<IpAddr>() {
+        Ok(ip) => SocketAddr::new(ip, 161).to_string(),
+        Err(_) if host.contains(':') => host.to_string(),
+        Err(_) => format!("{}:161", host),
+    };
+    let addr = tokio::net::lookup_host(&target)
+        .await
+        .map_err(|e| format!("cannot resolve SNMP host {}: {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("cannot resolve SNMP host {}", host))?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    // Positive and small enough for a 4-byte INTEGER
+    let request_id = (now.subsec_nanos() & 0x3fff_ffff).to_be_bytes();
+
+    let varbind = ber(0x30, &[ber(0x06, &oid), ber(0x05, &[])].concat());
+    let pdu = ber(
+        0xa0,
+        &[
+            ber(0x02, &request_id),
+            ber(0x02, &[0]),
+            ber(0x02, &[0]),
+            ber(0x30, &varbind),
+        ]
+        .concat(),
+    );
+    // Version 1 is SNMP v2c
+    let message = ber(
+        0x30,
+        &[ber(0x02, &[1]), ber(0x04, community.as_bytes()), pdu].concat(),
+    );
+
+    let bind: SocketAddr = if addr.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind)
+        .await
+        .map_err(|e| format!("cannot open UDP socket: {}", e))?;
+    socket
+        .connect(addr)
+        .await
+        .map_err(|e| format!("cannot reach SNMP host {}: {}", addr, e))?;
+    socket
+        .send(&message)
+        .await
+        .map_err(|e| format!("failed to send SNMP request to {}: {}", addr, e))?;
+
+    let mut buf = [0u8; 1500];
+    let deadline = tokio::time::Instant::now() + SNMP_TIMEOUT;
+    loop {
+        let len = tokio::time::timeout_at(deadline, socket.recv(&mut buf))
+            .await
+            // Agents drop requests with a wrong community silently
+            .map_err(|_| format!("no SNMP answer from {} - check host and community", addr))?
+            .map_err(|e| format!("failed to read SNMP answer from {}: {}", addr, e))?;
+        if let Some(result) = parse_snmp_response(&buf[..len], &request_id) {
+            return result;
+        }
+    }
+}
+
+// Returns None for datagrams that are not the response to this request
+fn parse_snmp_response(msg: &[u8], request_id: &[u8]) -> Option<Result<IpAddr, String>> {
+    let (0x30, message) = read_ber(msg, &mut 0)? else {
+        return None;
+    };
+    let mut pos = 0;
+    read_ber(message, &mut pos)?; // version
+    read_ber(message, &mut pos)?; // community
+    let (0xa2, pdu) = read_ber(message, &mut pos)? else {
+        return None;
+    };
+
+    let mut pos = 0;
+    let (_, id) = read_ber(pdu, &mut pos)?;
+    if id
+        .iter()
+        .skip_while(|b| **b == 0)
+        .ne(request_id.iter().skip_while(|b| **b == 0))
+    {
+        return None;
+    }
+    let (_, error_status) = read_ber(pdu, &mut pos)?;
+    read_ber(pdu, &mut pos)?; // error index
+    if error_status.iter().any(|b| *b != 0) {
+        return Some(Err(format!(
+            "SNMP error status {}",
+            error_status.last().copied().unwrap_or_default()
+        )));
+    }
+    let (_, varbinds) = read_ber(pdu, &mut pos)?;
+    let (_, varbind) = read_ber(varbinds, &mut 0)?;
+    let mut pos = 0;
+    read_ber(varbind, &mut pos)?; // oid
+    let (tag, value) = read_ber(varbind, &mut pos)?;
+
+    let ip = match tag {
+        // IpAddress
+        0x40 => <[u8; 4]>::try_from(value).ok().map(IpAddr::from),
+        0x04 => match value.len() {
+            4 => <[u8; 4]>::try_from(value).ok().map(IpAddr::from),
+            16 => <[u8; 16]>::try_from(value).ok().map(IpAddr::from),
+            _ => std::str::from_utf8(value)
+                .ok()
+                .and_then(|text| text.trim().parse().ok()),
+        },
+        0x80 | 0x81 => return Some(Err("the OID does not exist on the SNMP host".to_string())),
+        _ => None,
+    };
+    Some(ip.ok_or_else(|| "the OID does not hold an IP address".to_string()))
+}
+
+fn ber(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match content.len() {
+        len @ 0..=0x7f => out.push(len as u8),
+        len @ 0x80..=0xff => out.extend_from_slice(&[0x81, len as u8]),
+        len => {
+            out.push(0x82);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn read_ber<'a>(buf: &'a [u8], pos: &mut usize) -> Option<(u8, &'a [u8])> {
+    let tag = *buf.get(*pos)?;
+    let first = *buf.get(*pos + 1)? as usize;
+    let (len, header) = if first & 0x80 == 0 {
+        (first, 2)
+    } else {
+        // The length comes from the agent; one that does not fit is malformed
+        let count = first & 0x7f;
+        if count > std::mem::size_of::<usize>() {
+            return None;
+        }
+        let bytes = buf.get(*pos + 2..*pos + 2 + count)?;
+        (
+            bytes.iter().fold(0usize, |len, b| len << 8 | *b as usize),
+            2 + count,
+        )
+    };
+    let end = pos.checked_add(header)?.checked_add(len)?;
+    let content = buf.get(*pos + header..end)?;
+    *pos = end;
+    Some((tag, content))
+}
+
+// Encodes a dotted OID such as `1.3.6.1.2.1.4.20.1.1`
+fn encode_oid(oid: &str) -> Option<Vec<u8>> {
+    let arcs: Vec<u32> = oid
+        .trim_start_matches('.')
+        .split('.')
+        .map(|arc| arc.parse().ok())
+        .collect::<Option<_>>()?;
+    if arcs.len() < 2 || arcs[0] > 2 {
+        return None;
+    }
+    let mut out = Vec::new();
+    for arc in std::iter::once(arcs[0] * 40 + arcs[1]).chain(arcs[2..].iter().copied()) {
+        let mut bytes = vec![(arc & 0x7f) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            bytes.push((rest & 0x7f) as u8 | 0x80);
+            rest >>= 7;
+        }
+        out.extend(bytes.iter().rev());
+    }
+    Some(out)
+}
+
+// Sends a STUN binding request (RFC 5389) and returns the mapped address
+async fn from_stun(server: &str, family: IpFamily) -> Result<IpAddr, String> {
+    let server_name = stun_address(server);
+    let addrs = tokio::net::lookup_host(&server_name)
+        .await
+        .map_err(|e| format!("dns - cannot resolve STUN server {}: {}", server, e))?;
+    let addr = addrs
+        .into_iter()
+        .find(|addr| IpFamily::of(&addr.ip()) == family)
+        .ok_or_else(|| {
+            format!(
+                "STUN server {} has no {} address",
+                server,
+                family.record_type()
+            )
+        })?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut transaction = [0u8; 12];
+    transaction[..8].copy_from_slice(&(now.as_nanos() as u64).to_be_bytes());
+    transaction[8..].copy_from_slice(&std::process::id().to_be_bytes());
+
+    // Binding request without attributes
+    let mut request = vec![0x00, 0x01, 0x00, 0x00];
+    request.extend_from_slice(&STUN_MAGIC_COOKIE);
+    request.extend_from_slice(&transaction);
+
+    let bind: SocketAddr = if addr.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind)
+        .await
+        .map_err(|e| format!("cannot open UDP socket: {}", e))?;
+    socket
+        .connect(addr)
+        .await
+        .map_err(|e| format!("connection failed - cannot reach {}: {}", addr, e))?;
+    socket
+        .send(&request)
+        .await
+        .map_err(|e| format!("failed to send STUN request to {}: {}", addr, e))?;
+
+    let mut buf = [0u8; 576];
+    let deadline = tokio::time::Instant::now() + STUN_TIMEOUT;
+    loop {
+        let len = tokio::time::timeout_at(deadline, socket.recv(&mut buf))
+            .await
+            .map_err(|_| format!("timeout - no answer from STUN server {}", addr))?
+            .map_err(|e| format!("failed to read answer from {}: {}", addr, e))?;
+        // Only a binding success response to this transaction
+        if len >= 20 && buf[0..2] == [0x01, 0x01] && buf[8..20] == transaction {
+            return parse_stun_response(&buf[..len], &transaction)
+                .ok_or_else(|| format!("STUN server {} sent no mapped address", addr));
+        }
+    }
+}
+
+// Returns the XOR-MAPPED-ADDRESS of a binding response, or the plain
+// MAPPED-ADDRESS of servers that predate RFC 5389
+// The server as host:port; the port defaults to the standard STUN port
+fn stun_address(server: &str) -> String {
+    if let Some(rest) = server.strip_prefix('[') {
+        return match rest.split_once(']') {
+            Some((host, port)) => match port.strip_prefix(':') {
+                Some(port) => format!("[{}]:{}", host, port),
+                None => format!("[{}]:3478", host),
+            },
+            None => format!("[{}]:3478", rest),
+        };
+    }
+    match server.rsplit_once(':') {
+        Some((host, _)) if !host.contains(':') => server.to_string(),
+        Some(_) => format!("[{}]:3478", server),
+        None => format!("{}:3478", server),
+    }
+}
+
+fn parse_stun_response(msg: &[u8], transaction: &[u8; 12]) -> Option<IpAddr> {
+    let length = u16::from_be_bytes([msg[2], msg[3]]) as usize;
+    let attributes = msg.get(20..20 + length)?;
+
+    let mut mapped = None;
+    let mut pos = 0;
+    while let Some(header) = attributes.get(pos..pos + 4) {
+        let kind = u16::from_be_bytes([header[0], header[1]]);
+        let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let value = attributes.get(pos + 4..pos + 4 + len)?;
+        // Attributes are padded to four bytes
+        pos += 4 + len.div_ceil(4) * 4;
+
+        // Too short for an address attribute
+        if value.len() < 8 {
+            continue;
+        }
+        let address = &value[4..];
+        match (kind, value[1]) {
+            // XOR-MAPPED-ADDRESS, IPv4
+            (0x0020, 0x01) => {
+                let mut ip = <[u8; 4]>::try_from(address).ok()?;
+                ip.iter_mut()
+                    .zip(STUN_MAGIC_COOKIE)
+                    .for_each(|(b, k)| *b ^= k);
+                return Some(IpAddr::from(ip));
+            }
+            // XOR-MAPPED-ADDRESS, IPv6
+            (0x0020, 0x02) => {
+                let mut ip = <[u8; 16]>::try_from(address).ok()?;
+                ip.iter_mut()
+                    .zip(STUN_MAGIC_COOKIE.iter().chain(transaction))
+                    .for_each(|(b, k)| *b ^= k);
+                return Some(IpAddr::from(ip));
+            }
+            (0x0001, 0x01) => mapped = <[u8; 4]>::try_from(address).ok().map(IpAddr::from),
+            (0x0001, 0x02) => mapped = <[u8; 16]>::try_from(address).ok().map(IpAddr::from),
+            _ => {}
+        }
+    }
+    mapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    const TRANSACTION: [u8; 12] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+
+    fn stun_response(attributes: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (kind, value) in attributes {
+            body.extend_from_slice(&kind.to_be_bytes());
+            body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            body.extend_from_slice(value);
+            body.resize(body.len().div_ceil(4) * 4, 0);
+        }
+        let mut msg = vec![0x01, 0x01];
+        msg.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        msg.extend_from_slice(&STUN_MAGIC_COOKIE);
+        msg.extend_from_slice(&TRANSACTION);
+        msg.extend(body);
+        msg
+    }
+
+    #[test]
+    fn stun_xor_mapped_address() {
+        // 203.0.113.7 XOR the magic cookie
+        let ipv4 = stun_response(&[(0x0020, vec![0, 0x01, 0x21, 0x12, 0xea, 0x12, 0xd5, 0x45])]);
+        assert_eq!(
+            parse_stun_response(&ipv4, &TRANSACTION),
+            Some("203.0.113.7".parse().unwrap())
+        );
+
+        let ip: Ipv6Addr = "2001:db8::7".parse().unwrap();
+        let mut value = vec![0, 0x02, 0, 0];
+        value.extend(
+            ip.octets()
+                .iter()
+                .zip(STUN_MAGIC_COOKIE.iter().chain(&TRANSACTION))
+                .map(|(b, k)| b ^ k),
+        );
+        let ipv6 = stun_response(&[(0x0020, value)]);
+        assert_eq!(
+            parse_stun_response(&ipv6, &TRANSACTION),
+            Some(IpAddr::V6(ip))
+        );
+    }
+
+    #[test]
+    fn stun_prefers_xor_mapped_over_mapped_address() {
+        let msg = stun_response(&[
+            // SOFTWARE, with padding
+            (0x8022, b"test".to_vec()),
+            (0x0001, vec![0, 0x01, 0, 0, 198, 51, 100, 7]),
+            (0x0020, vec![0, 0x01, 0x21, 0x12, 0xea, 0x12, 0xd5, 0x45]),
+        ]);
+        assert_eq!(
+            parse_stun_response(&msg, &TRANSACTION),
+            Some("203.0.113.7".parse().unwrap())
+        );
+
+        let legacy = stun_response(&[(0x0001, vec![0, 0x01, 0, 0, 198, 51, 100, 7])]);
+        assert_eq!(
+            parse_stun_response(&legacy, &TRANSACTION),
+            Some("198.51.100.7".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn stun_server_ports() {
+        for (server, address) in [
+            ("stun.example.com", "stun.example.com:3478"),
+            ("stun.example.com:19302", "stun.example.com:19302"),
+            ("2001:db8::1", "[2001:db8::1]:3478"),
+            ("[2001:db8::1]", "[2001:db8::1]:3478"),
+            ("[2001:db8::1]:19302", "[2001:db8::1]:19302"),
+        ] {
+            assert_eq!(stun_address(server), address);
+        }
+    }
+
+    #[test]
+    fn stun_rejects_truncated_messages() {
+        let mut msg = stun_response(&[(0x0020, vec![0, 0x01, 0x21, 0x12, 0xea, 0x12, 0xd5, 0x45])]);
+        msg.truncate(msg.len() - 2);
+        assert_eq!(parse_stun_response(&msg, &TRANSACTION), None);
+        assert_eq!(parse_stun_response(&stun_response(&[]), &TRANSACTION), None);
+    }
+
+    fn snmp_response(request_id: &[u8], error_status: u8, tag: u8, value: &[u8]) -> Vec<u8> {
+        let oid = encode_oid("1.3.6.1.2.1.4.20.1.1").unwrap();
+        let varbind = ber(0x30, &[ber(0x06, &oid), ber(tag, value)].concat());
+        let pdu = ber(
+            0xa2,
+            &[
+                ber(0x02, request_id),
+                ber(0x02, &[error_status]),
+                ber(0x02, &[0]),
+                ber(0x30, &varbind),
+            ]
+            .concat(),
+        );
+        ber(0x30, &[ber(0x02, &[1]), ber(0x04, b"public"), pdu].concat())
+    }
+
+    #[test]
+    fn snmp_response_values() {
+        let check = |error_status: u8, tag: u8, value: &[u8], expected: Result<&str, &str>| {
+            let id = [0, 0, 0x12, 0x34];
+            let msg = snmp_response(&id, error_status, tag, value);
+            let expected = expected
+                .map(|ip| ip.parse::<IpAddr>().unwrap())
+                .map_err(str::to_string);
+            assert_eq!(
+                parse_snmp_response(&msg, &id),
+                Some(expected),
+                "tag {:#x}",
+                tag
+            );
+        };
+        check(0, 0x40, &[203, 0, 113, 7], Ok("203.0.113.7"));
+        check(0, 0x04, b"203.0.113.7\n", Ok("203.0.113.7"));
+        let ipv6 = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7];
+        check(0, 0x04, &ipv6, Ok("2001:db8::7"));
+        check(0, 0x80, &[], Err("the OID does not exist on the SNMP host"));
+        check(0, 0x02, &[1], Err("the OID does not hold an IP address"));
+        check(2, 0x05, &[], Err("SNMP error status 2"));
+    }
+
+    #[test]
+    fn snmp_ignores_other_requests_and_garbage() {
+        let msg = snmp_response(&[0x12, 0x34], 0, 0x40, &[203, 0, 113, 7]);
+        // Leading zero bytes of the request id do not matter
+        assert!(parse_snmp_response(&msg, &[0, 0, 0x12, 0x34]).is_some());
+        assert_eq!(parse_snmp_response(&msg, &[0, 0, 0x12, 0x35]), None);
+        assert_eq!(
+            parse_snmp_response(&msg[..msg.len() - 1], &[0x12, 0x34]),
+            None
+        );
+        assert_eq!(parse_snmp_response(b"\x04\x00", &[0x12, 0x34]), None);
+    }
+
+    #[test]
+    fn read_ber_lengths() {
+        let long = ber(0x04, &[7; 300]);
+        assert_eq!(&long[..4], &[0x04, 0x82, 0x01, 0x2c]);
+        let mut pos = 0;
+        assert_eq!(read_ber(&long, &mut pos), Some((0x04, &[7u8; 300][..])));
+        assert_eq!(pos, long.len());
+
+        // Lengths past the buffer, too wide for usize or overflowing
+        let cases: [&[u8]; 4] = [
+            &[0x04, 0x05, 1, 2],
+            &[0x04, 0x89, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+            &[0x04, 0x88, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            &[0x04, 0x88, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xf0],
+        ];
+        for buf in cases {
+            assert_eq!(read_ber(buf, &mut 0), None, "{:x?}", buf);
+        }
+    }
+
+    #[test]
+    fn encode_oid_arcs() {
+        assert_eq!(
+            encode_oid("1.3.6.1.2.1.4.20.1.1"),
+            Some(vec![0x2b, 6, 1, 2, 1, 4, 20, 1, 1])
+        );
+        // Arcs above 127 take several bytes
+        assert_eq!(
+            encode_oid(".1.3.6.1.4.1.14988"),
+            Some(vec![0x2b, 6, 1, 4, 1, 0xf5, 0x0c])
+        );
+        assert_eq!(encode_oid("1"), None);
+        assert_eq!(encode_oid("3.1"), None);
+        assert_eq!(encode_oid("1.3.x"), None);
+    }
+
+    fn dns_response(qtype: u16, qclass: u16, rdata: &[u8]) -> Vec<u8> {
+        let mut msg = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+        for label in ["myip", "opendns", "com"] {
+            msg.push(label.len() as u8);
+            msg.extend_from_slice(label.as_bytes());
+        }
+        msg.push(0);
+        msg.extend_from_slice(&qtype.to_be_bytes());
+        msg.extend_from_slice(&qclass.to_be_bytes());
+        // Answer named by a pointer to the question
+        msg.extend_from_slice(&[0xc0, 12]);
+        msg.extend_from_slice(&qtype.to_be_bytes());
+        msg.extend_from_slice(&qclass.to_be_bytes());
+        msg.extend_from_slice(&[0, 0, 0, 0]);
+        msg.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        msg.extend_from_slice(rdata);
+        msg
+    }
+
+    #[test]
+    fn dns_answers() {
+        assert_eq!(
+            parse_answer(&dns_response(1, 1, &[203, 0, 113, 7]), 1),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        let ipv6: Ipv6Addr = "2001:db8::7".parse().unwrap();
+        assert_eq!(
+            parse_answer(&dns_response(28, 1, &ipv6.octets()), 28),
+            Some(IpAddr::V6(ipv6))
+        );
+        let mut txt = vec![11];
+        txt.extend_from_slice(b"203.0.113.7");
+        assert_eq!(
+            parse_answer(&dns_response(16, 3, &txt), 16),
+            Some("203.0.113.7".parse().unwrap())
+        );
+
+        // Wrong type, short address, truncated message
+        assert_eq!(
+            parse_answer(&dns_response(1, 1, &[203, 0, 113, 7]), 28),
+            None
+        );
+        assert_eq!(parse_answer(&dns_response(1, 1, &[203, 0, 113]), 1), None);
+        let msg = dns_response(1, 1, &[203, 0, 113, 7]);
+        assert_eq!(parse_answer(&msg[..msg.len() - 1], 1), None);
+    }
+
+    #[test]
+    fn first_address_of_command_output() {
+        let cases = [
+            ("203.0.113.7\n", Some("203.0.113.7")),
+            ("\n  2001:db8::7/64 \nother", Some("2001:db8::7")),
+            ("", None),
+            ("not an address", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(
+                first_address(text).ok(),
+                expected.map(|ip| ip.parse::<IpAddr>().unwrap()),
+                "{:?}",
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,461 @@
+use crate::IpFamily;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const TYPE_A: u16 = 1;
+const TYPE_NS: u16 = 2;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+// Where the current record of a host is looked up before its first update:
+// `auto`, `off`, `system`, `authoritative` or the address of a resolver
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RecordCheck {
+    // The authoritative servers, or the system resolver where outgoing DNS
+    // is blocked
+    #[default]
+    Auto,
+    Off,
+    System,
+    // The zone's own name servers, which see an update right away
+    Authoritative,
+    Resolver(IpAddr),
+}
+
+impl TryFrom<String> for RecordCheck {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        match value.as_str() {
+            "auto" => Ok(RecordCheck::Auto),
+            "off" => Ok(RecordCheck::Off),
+            "system" => Ok(RecordCheck::System),
+            "authoritative" => Ok(RecordCheck::Authoritative),
+            other => other.parse().map(RecordCheck::Resolver).map_err(|_| {
+                format!(
+                    "record_check must be auto, off, system, authoritative or a resolver address, not '{}'",
+                    other
+                )
+            }),
+        }
+    }
+}
+
+impl From<RecordCheck> for String {
+    fn from(check: RecordCheck) -> Self {
+        match check {
+            RecordCheck::Auto => "auto".to_string(),
+            RecordCheck::Off => "off".to_string(),
+            RecordCheck::System => "system".to_string(),
+            RecordCheck::Authoritative => "authoritative".to_string(),
+            RecordCheck::Resolver(ip) => ip.to_string(),
+        }
+    }
+}
+
+// Polls the record after an update until it holds the new address
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifyConfig {
+    #[serde(default = "default_verify_resolver")]
+    pub resolver: RecordCheck,
+    // Seconds to wait for the record to change
+    #[serde(default = "default_verify_timeout")]
+    pub timeout: u64,
+}
+
+fn default_verify_resolver() -> RecordCheck {
+    RecordCheck::Authoritative
+}
+
+fn default_verify_timeout() -> u64 {
+    300
+}
+
+impl VerifyConfig {
+    pub fn normalize(&mut self) {
+        if self.timeout < 30 {
+            self.timeout = 30;
+        }
+        // Turning verification off is done by leaving out verify_update
+        if self.resolver == RecordCheck::Off {
+            self.resolver = default_verify_resolver();
+        }
+    }
+}
+
+// An answer record; `rdata` is its position in the message, since names in it
+// may point elsewhere in the message
+pub struct Answer {
+    pub rtype: u16,
+    pub rdata: Range<usize>,
+}
+
+// The addresses the hostname currently resolves to
+pub async fn lookup(
+    check: &RecordCheck,
+    hostname: &str,
+    zone: &str,
+    family: IpFamily,
+) -> Result<Vec<IpAddr>, String> {
+    match check {
+        RecordCheck::Auto => match lookup_authoritative(hostname, zone, family).await {
+            Ok(addresses) => Ok(addresses),
+            Err(authoritative) => lookup_system(hostname, family)
+                .await
+                .map_err(|system| format!("{}; {}", authoritative, system)),
+        },
+        RecordCheck::Off => Err("record lookups are off".to_string()),
+        RecordCheck::System => lookup_system(hostname, family).await,
+        RecordCheck::Resolver(resolver) => {
+            let msg = query(
+                SocketAddr::new(*resolver, 53),
+                hostname,
+                qtype(family),
+                true,
+            )
+            .await?;
+            Ok(addresses(&msg, family))
+        }
+        RecordCheck::Authoritative => lookup_authoritative(hostname, zone, family).await,
+    }
+}
+
+async fn lookup_system(hostname: &str, family: IpFamily) -> Result<Vec<IpAddr>, String> {
+    Ok(tokio::net::lookup_host((hostname, 0))
+        .await
+        .map_err(|e| format!("cannot resolve {}: {}", hostname, e))?
+        .map(|addr| addr.ip())
+        .filter(|ip| IpFamily::of(ip) == family)
+        .collect())
+}
+
+async fn lookup_authoritative(
+    hostname: &str,
+    zone: &str,
+    family: IpFamily,
+) -> Result<Vec<IpAddr>, String> {
+    let mut last_error = String::new();
+    for server in name_servers(hostname, zone).await? {
+        let Ok(Some(addr)) = tokio::net::lookup_host((server.as_str(), 53))
+            .await
+            .map(|mut addrs| addrs.next())
+        else {
+            last_error = format!("cannot resolve name server {}", server);
+            continue;
+        };
+        match query(addr, hostname, qtype(family), false).await {
+            Ok(msg) => return Ok(addresses(&msg, family)),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+fn qtype(family: IpFamily) -> u16 {
+    match family {
+        IpFamily::V4 => TYPE_A,
+        IpFamily::V6 => TYPE_AAAA,
+    }
+}
+
+fn addresses(msg: &[u8], family: IpFamily) -> Vec<IpAddr> {
+    answers(msg)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|answer| answer.rtype == qtype(family))
+        .filter_map(|answer| match &msg[answer.rdata] {
+            rdata if rdata.len() == 4 => <[u8; 4]>::try_from(rdata).ok().map(IpAddr::from),
+            rdata => <[u8; 16]>::try_from(rdata).ok().map(IpAddr::from),
+        })
+        .collect()
+}
+
+// Names of the servers for the zone, asked from the system's resolver. Without
+// a configured zone the closest enclosing name with NS records is taken.
+async fn name_servers(hostname: &str, zone: &str) -> Result<Vec<String>, String> {
+    let resolver = SocketAddr::new(system_resolver().await, 53);
+    let hostname = hostname.trim_end_matches('.');
+    let candidates: Vec<&str> = if zone.is_empty() {
+        hostname
+            .match_indices('.')
+            .map(|(i, _)| &hostname[i + 1..])
+            .filter(|name| name.contains('.'))
+            .collect()
+    } else {
+        vec![zone.trim_end_matches('.')]
+    };
+
+    for name in std::iter::once(hostname).chain(candidates) {
+        let msg = query(resolver, name, TYPE_NS, true).await?;
+        let servers: Vec<String> = answers(&msg)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|answer| answer.rtype == TYPE_NS)
+            .filter_map(|answer| read_name(&msg, answer.rdata.start))
+            .collect();
+        if !servers.is_empty() {
+            return Ok(servers);
+        }
+    }
+    Err(format!("no name servers found for {}", hostname))
+}
+
+// First nameserver in /etc/resolv.conf, or Cloudflare's
+async fn system_resolver() -> IpAddr {
+    tokio::fs::read_to_string("/etc/resolv.conf")
+        .await
+        .ok()
+        .and_then(|conf| {
+            conf.lines().find_map(|line| {
+                let mut parts = line.split_whitespace();
+                (parts.next() == Some("nameserver"))
+                    .then(|| parts.next()?.parse().ok())
+                    .flatten()
+            })
+        })
+        .unwrap_or_else(|| [1, 1, 1, 1].into())
+}
+
+// Sends one question over UDP and returns the matching answer
+async fn query(
+    server: SocketAddr,
+    name: &str,
+    qtype: u16,
+    recursive: bool,
+) -> Result<Vec<u8>, String> {
+    query_class(server, name, qtype, CLASS_IN, recursive).await
+}
+
+pub async fn query_class(
+    server: SocketAddr,
+    name: &str,
+    qtype: u16,
+    qclass: u16,
+    recursive: bool,
+) -> Result<Vec<u8>, String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let id = (now.subsec_nanos() ^ (now.as_secs() as u32)) as u16;
+    let mut query = Vec::with_capacity(64);
+    query.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired or not, one question
+    query.extend_from_slice(&[recursive as u8, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&qtype.to_be_bytes());
+    query.extend_from_slice(&qclass.to_be_bytes());
+
+    let bind: SocketAddr = if server.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind)
+        .await
+        .map_err(|e| format!("cannot open UDP socket: {}", e))?;
+    socket
+        .connect(server)
+        .await
+        .map_err(|e| format!("connection failed - cannot reach {}: {}", server, e))?;
+    socket
+        .send(&query)
+        .await
+        .map_err(|e| format!("failed to query {}: {}", server, e))?;
+
+    let mut buf = [0u8; 1232];
+    let deadline = tokio::time::Instant::now() + TIMEOUT;
+    loop {
+        let len = tokio::time::timeout_at(deadline, socket.recv(&mut buf))
+            .await
+            .map_err(|_| format!("timeout - no answer from {}", server))?
+            .map_err(|e| format!("failed to read answer from {}: {}", server, e))?;
+        // Ignore stray datagrams that are not the answer to this query
+        if len >= 12 && buf[0..2] == id.to_be_bytes() && buf[2] & 0x80 != 0 {
+            return match buf[3] & 0x0f {
+                // NXDOMAIN is an answer too: there is no record
+                0 | 3 => Ok(buf[..len].to_vec()),
+                rcode => Err(format!(
+                    "{} answered with error {} for {}",
+                    server, rcode, name
+                )),
+            };
+        }
+    }
+}
+
+// The answer section; None for a malformed message
+pub fn answers(msg: &[u8]) -> Option<Vec<Answer>> {
+    let questions = u16::from_be_bytes([*msg.get(4)?, *msg.get(5)?]);
+    let count = u16::from_be_bytes([*msg.get(6)?, *msg.get(7)?]);
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(msg, pos)? + 4;
+    }
+    let mut answers = Vec::new();
+    for _ in 0..count {
+        pos = skip_name(msg, pos)?;
+        let header = msg.get(pos..pos + 10)?;
+        let rtype = u16::from_be_bytes([header[0], header[1]]);
+        let rdlength = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let rdata = pos + 10..pos + 10 + rdlength;
+        msg.get(rdata.clone())?;
+        pos = rdata.end;
+        answers.push(Answer { rtype, rdata });
+    }
+    Some(answers)
+}
+
+// Returns the position after a (possibly compressed) name
+fn skip_name(msg: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *msg.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            // Compression pointer, always the end of the name
+            l if l & 0xc0 == 0xc0 => return Some(pos + 2),
+            l => pos += 1 + l as usize,
+        }
+    }
+}
+
+fn read_name(msg: &[u8], mut pos: usize) -> Option<String> {
+    let mut labels = Vec::new();
+    // Bounds the pointers followed, so a pointer loop cannot hang
+    for _ in 0..128 {
+        let len = *msg.get(pos)? as usize;
+        match len {
+            0 => return Some(labels.join(".")),
+            l if l & 0xc0 == 0xc0 => {
+                pos = (l & 0x3f) << 8 | *msg.get(pos + 1)? as usize;
+            }
+            l => {
+                labels.push(String::from_utf8_lossy(msg.get(pos + 1..pos + 1 + l)?).to_string());
+                pos += 1 + l;
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A response to `home.example.org` with the given answers, each named by a
+    // pointer to the question
+    fn response(answers: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut msg = vec![
+            0x12,
+            0x34,
+            0x81,
+            0x80,
+            0,
+            1,
+            0,
+            answers.len() as u8,
+            0,
+            0,
+            0,
+            0,
+        ];
+        for label in ["home", "example", "org"] {
+            msg.push(label.len() as u8);
+            msg.extend_from_slice(label.as_bytes());
+        }
+        msg.extend_from_slice(&[0, 0, 1, 0, 1]);
+        for (rtype, rdata) in answers {
+            msg.extend_from_slice(&[0xc0, 12]);
+            msg.extend_from_slice(&rtype.to_be_bytes());
+            msg.extend_from_slice(&[0, 1, 0, 0, 0x0e, 0x10]);
+            msg.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            msg.extend_from_slice(rdata);
+        }
+        msg
+    }
+
+    #[test]
+    fn answers_and_addresses() {
+        let ipv6: std::net::Ipv6Addr = "2001:db8::7".parse().unwrap();
+        let msg = response(&[
+            (TYPE_A, &[203, 0, 113, 7]),
+            (TYPE_AAAA, &ipv6.octets()),
+            (TYPE_A, &[198, 51, 100, 7]),
+        ]);
+        let found = answers(&msg).unwrap();
+        assert_eq!(
+            found.iter().map(|a| a.rtype).collect::<Vec<_>>(),
+            [TYPE_A, TYPE_AAAA, TYPE_A]
+        );
+        assert_eq!(&msg[found[0].rdata.clone()], &[203, 0, 113, 7]);
+
+        assert_eq!(
+            addresses(&msg, IpFamily::V4),
+            [
+                "203.0.113.7".parse::<IpAddr>().unwrap(),
+                "198.51.100.7".parse().unwrap()
+            ]
+        );
+        assert_eq!(addresses(&msg, IpFamily::V6), [IpAddr::V6(ipv6)]);
+    }
+
+    #[test]
+    fn malformed_answers() {
+        let msg = response(&[(TYPE_A, &[203, 0, 113, 7])]);
+        assert!(answers(&msg[..msg.len() - 1]).is_none());
+        assert!(answers(&msg[..6]).is_none());
+        // No answers is not an error
+        assert_eq!(answers(&response(&[])).unwrap().len(), 0);
+        assert!(addresses(&msg[..msg.len() - 1], IpFamily::V4).is_empty());
+        // A wrong-sized address is skipped
+        assert!(addresses(&response(&[(TYPE_A, &[203, 0, 113])]), IpFamily::V4).is_empty());
+    }
+
+    #[test]
+    fn names_follow_compression_pointers() {
+        // ns1 followed by a pointer to example.org in the question
+        let msg = response(&[(TYPE_NS, &[3, b'n', b's', b'1', 0xc0, 17])]);
+        let answer = &answers(&msg).unwrap()[0];
+        assert_eq!(
+            read_name(&msg, answer.rdata.start).as_deref(),
+            Some("ns1.example.org")
+        );
+        assert_eq!(read_name(&msg, 12).as_deref(), Some("home.example.org"));
+        assert_eq!(skip_name(&msg, 12), Some(30));
+        assert_eq!(skip_name(&msg, answer.rdata.start), Some(answer.rdata.end));
+    }
+
+    #[test]
+    fn name_pointer_loops_end() {
+        let mut msg = response(&[]);
+        // The question name points at itself
+        msg[12] = 0xc0;
+        msg[13] = 12;
+        assert_eq!(read_name(&msg, 12), None);
+        assert_eq!(read_name(&msg, msg.len()), None);
+    }
+
+    #[test]
+    fn record_check_values() {
+        for (value, check) in [
+            ("auto", RecordCheck::Auto),
+            ("off", RecordCheck::Off),
+            ("system", RecordCheck::System),
+            ("authoritative", RecordCheck::Authoritative),
+            ("9.9.9.9", RecordCheck::Resolver([9, 9, 9, 9].into())),
+        ] {
+            assert_eq!(RecordCheck::try_from(value.to_string()), Ok(check.clone()));
+            assert_eq!(String::from(check), value);
+        }
+        assert!(RecordCheck::try_from("cached".to_string()).is_err());
+    }
+}
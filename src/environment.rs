@@ -0,0 +1,146 @@
+use crate::{Config, HostConfig};
+use serde_json::{Map, Value};
+
+const PREFIX: &str = "DDNS_";
+// Read by `once --ip`, not part of the config
+const RESERVED: &[&str] = &["DDNS_ONCE_IP"];
+
+// DDNS_* variables that set config values, as lowercase keys without the prefix
+fn variables() -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| !RESERVED.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.strip_prefix(PREFIX)?.to_lowercase(), value)))
+        .collect();
+    // Parents before their nested keys, e.g. DDNS_LISTEN before DDNS_LISTEN__PASS
+    vars.sort();
+    vars
+}
+
+// Whether the variables describe a host at all. Others such as DDNS_INTERVAL
+// are commonly set for a config file and would hide that it is missing.
+pub fn is_configured() -> bool {
+    variables()
+        .iter()
+        .any(|(key, _)| matches!(key.as_str(), "provider" | "hostname" | "hosts"))
+}
+
+// Applies the variables over the config read from files. Settings of a single
+// host apply to every entry of `hosts` when there is one, and to the top-level
+// host otherwise. Nested keys are separated by a double underscore.
+pub fn apply(config: &mut Map<String, Value>) -> Result<(), String> {
+    // Every field at its default value tells the type a variable is read as
+    let defaults = serde_json::from_value::<Config>(Value::Object(Map::new()))
+        .ok()
+        .and_then(|defaults| serde_json::to_value(defaults).ok())
+        .unwrap_or_default();
+    let host_keys: Vec<String> = match serde_json::to_value(HostConfig::default()) {
+        Ok(Value::Object(host)) => host.into_iter().map(|(key, _)| key).collect(),
+        _ => Vec::new(),
+    };
+
+    let mut host_vars = Vec::new();
+    let mut hosts = None;
+    for (key, value) in variables() {
+        let path: Vec<&str> = key.split("__").collect();
+        if key == "hosts" {
+            hosts = Some(value);
+        } else if host_keys.iter().any(|host_key| host_key == path[0]) {
+            host_vars.push((key, value));
+        } else {
+            set(config, &path, &value, &defaults);
+        }
+    }
+
+    if let Some(hosts) = hosts {
+        let hosts = parse_hosts(config, &hosts, &host_keys)?;
+        config.insert("hosts".to_string(), hosts);
+    }
+
+    match config.get_mut("hosts") {
+        Some(Value::Array(entries)) if !entries.is_empty() => {
+            for entry in entries.iter_mut().filter_map(Value::as_object_mut) {
+                for (key, value) in &host_vars {
+                    set(
+                        entry,
+                        &key.split("__").collect::<Vec<_>>(),
+                        value,
+                        &defaults,
+                    );
+                }
+            }
+        }
+        _ => {
+            for (key, value) in &host_vars {
+                set(
+                    config,
+                    &key.split("__").collect::<Vec<_>>(),
+                    value,
+                    &defaults,
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// DDNS_HOSTS is either a JSON list of host objects or a comma-separated list of
+// hostnames that share the top-level host settings
+fn parse_hosts(
+    config: &mut Map<String, Value>,
+    hosts: &str,
+    host_keys: &[String],
+) -> Result<Value, String> {
+    if hosts.trim_start().starts_with('[') {
+        return serde_json::from_str(hosts)
+            .map_err(|e| format!("DDNS_HOSTS is not a valid JSON list: {}", e));
+    }
+
+    let shared: Map<String, Value> = host_keys
+        .iter()
+        .filter_map(|key| Some((key.clone(), config.remove(key)?)))
+        .collect();
+    Ok(hosts
+        .split(',')
+        .map(str::trim)
+        .filter(|hostname| !hostname.is_empty())
+        .map(|hostname| {
+            let mut host = shared.clone();
+            host.insert("hostname".to_string(), Value::from(hostname));
+            Value::Object(host)
+        })
+        .collect())
+}
+
+fn set(config: &mut Map<String, Value>, path: &[&str], value: &str, defaults: &Value) {
+    let default = path
+        .iter()
+        .try_fold(defaults, |default, key| default.get(*key));
+
+    let mut target = config;
+    for key in &path[..path.len() - 1] {
+        let entry = target.entry(key.to_string()).or_insert(Value::Null);
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+        target = entry
+            .as_object_mut()
+            .expect("entry was just made an object");
+    }
+    target.insert(path[path.len() - 1].to_string(), parse(value, default));
+}
+
+// Text fields keep the value as it is, so a numeric password stays a string.
+// List fields also take a comma-separated list; anything else is read as JSON
+// where it parses.
+fn parse(value: &str, default: Option<&Value>) -> Value {
+    match default {
+        Some(Value::String(_)) => Value::from(value),
+        Some(Value::Array(_)) if !value.trim_start().starts_with('[') => value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(Value::from)
+            .collect(),
+        _ => serde_json::from_str(value).unwrap_or_else(|_| Value::from(value)),
+    }
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CoordinationConfig {
+    pub lock_file: String,
+    #[serde(default = "default_lease")]
+    pub lease: u64,
+    #[serde(default)]
+    pub instance_id: Option<String>,
+}
+
+fn default_lease() -> u64 {
+    60
+}
+
+impl CoordinationConfig {
+    pub fn normalize(&mut self) {
+        if self.lease < 15 {
+            self.lease = 15;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LeaseRecord {
+    holder: String,
+    expires: u64,
+}
+
+pub enum LeaseStatus {
+    Held,
+    HeldBy(String),
+}
+
+// The hostname stays the same across restarts, so a restarted holder takes its
+// own lease back right away instead of waiting for it to expire
+pub fn default_instance_id() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "ddns-updater".to_string())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+async fn read_lease(path: &Path) -> Option<LeaseRecord> {
+    let contents = fs::read_to_string(path).await.ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+// Takes or renews the lease unless another instance holds an unexpired one.
+// The record is written to a temp file and renamed into place, then read back.
+// Two instances racing for an expired lease can still both read back their own
+// record and hold it for one cycle; the one whose write was overwritten sees the
+// other holder on its next renewal and steps back.
+pub async fn try_acquire(path: &Path, id: &str, lease: u64) -> std::io::Result<LeaseStatus> {
+    if let Some(record) = read_lease(path).await {
+        if record.holder != id && record.expires > now() {
+            return Ok(LeaseStatus::HeldBy(record.holder));
+        }
+    }
+
+    let record = LeaseRecord {
+        holder: id.to_string(),
+        expires: now() + lease,
+    };
+    let tmp = temp_path(path, id);
+    fs::write(&tmp, serde_json::to_vec(&record)?).await?;
+    fs::rename(&tmp, path).await?;
+
+    match read_lease(path).await {
+        Some(record) if record.holder != id => Ok(LeaseStatus::HeldBy(record.holder)),
+        _ => Ok(LeaseStatus::Held),
+    }
+}
+
+// Gives up the lease on shutdown so a standby can take over without waiting
+// for it to expire.
+pub async fn release(path: &Path, id: &str) -> std::io::Result<()> {
+    match read_lease(path).await {
+        Some(record) if record.holder == id => fs::remove_file(path).await,
+        _ => Ok(()),
+    }
+}
+
+fn temp_path(path: &Path, id: &str) -> PathBuf {
+    let id: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", id));
+    path.with_file_name(name)
+}
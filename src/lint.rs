@@ -0,0 +1,278 @@
+use crate::detect::IpSource;
+use crate::providers;
+use crate::{Config, HostConfig, IpVersion};
+
+const PLACEHOLDERS: &[&str] = &["changeme", "change_me", "password", "username", "secret"];
+
+// Returns warnings for settings that are valid but risky. Runs on the config as
+// written, before normalization adjusts any values.
+pub fn lint(config: &Config) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    // Messages name the host only when there is more than one
+    for host in &config.hosts {
+        let prefix = if config.hosts.len() > 1 {
+            format!("{}: ", host.name())
+        } else {
+            String::new()
+        };
+        for warning in lint_host(host, config.interval) {
+            warnings.push(format!("{}{}", prefix, warning));
+        }
+    }
+
+    let mut keys: Vec<String> = config.hosts.iter().map(|h| h.key()).collect();
+    keys.sort();
+    keys.dedup();
+    if keys.len() < config.hosts.len() {
+        warnings.push(
+            "several hosts share the same ddns and hostname - they overwrite each other"
+                .to_string(),
+        );
+    }
+
+    let legacy = &config.host;
+    if !legacy.ddns.is_empty()
+        || !legacy.user.is_empty()
+        || !legacy.pass.is_empty()
+        || !legacy.token.is_empty()
+        || !legacy.secret.is_empty()
+        || !legacy.pass_file.is_empty()
+        || !legacy.token_file.is_empty()
+        || !legacy.secret_file.is_empty()
+        || legacy.oauth2.is_some()
+    {
+        warnings.push(
+            "top-level host settings (user, pass, token, ddns, ...) are ignored when hosts is set"
+                .to_string(),
+        );
+    }
+
+    if config.drift_check > 0 && config.hosts.iter().all(|h| h.hostname.is_empty()) {
+        warnings.push(
+            "drift_check is set but no host has a hostname - drift is not checked".to_string(),
+        );
+    }
+
+    if config.http.update.bind_family {
+        warnings.push(
+            "http.update.bind_family has no effect - it only applies to detection".to_string(),
+        );
+    }
+
+    if matches!(config.ip_source.ipv4, IpSource::Interface { .. }) {
+        warnings.push(
+            "ip_source.ipv4 uses the interface source, which only detects IPv6 addresses"
+                .to_string(),
+        );
+    }
+    if matches!(config.ip_source.ipv6, IpSource::Router { .. }) {
+        warnings.push(
+            "ip_source.ipv6 uses the router source, which only detects IPv4 addresses".to_string(),
+        );
+    }
+    for (family, source) in [
+        ("ipv4", &config.ip_source.ipv4),
+        ("ipv6", &config.ip_source.ipv6),
+    ] {
+        let mut sources = vec![source];
+        let group = match source {
+            IpSource::Consensus {
+                sources: members,
+                required,
+            } => Some((members, required.unwrap_or(0))),
+            IpSource::Race {
+                sources: members,
+                agree,
+            } => Some((members, *agree)),
+            _ => None,
+        };
+        if let Some((members, required)) = group {
+            if required > members.len() {
+                warnings.push(format!(
+                    "ip_source.{} needs more agreeing sources than it lists - detection always fails",
+                    family
+                ));
+            }
+            if members
+                .iter()
+                .any(|member| matches!(member, IpSource::Consensus { .. } | IpSource::Race { .. }))
+            {
+                warnings.push(format!(
+                    "ip_source.{} nests a consensus or race source, which always fails",
+                    family
+                ));
+            }
+            sources.extend(members);
+        }
+        for source in sources {
+            match source {
+                IpSource::Mikrotik(mikrotik) if mikrotik.address.starts_with("http://") => {
+                    warnings.push(format!(
+                    "ip_source.{} reads the MikroTik router over plaintext HTTP - the password is sent unencrypted",
+                    family
+                ));
+                }
+                IpSource::Command { timeout: 0, .. } => {
+                    warnings.push(format!(
+                    "ip_source.{} has a command timeout of 0 - the command never gets to finish",
+                    family
+                ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[cfg(feature = "listen")]
+    if let Some(listen) = &config.listen {
+        if is_placeholder(&listen.pass) {
+            warnings.push("listen.pass looks like a placeholder".to_string());
+        }
+    }
+
+    warnings
+}
+
+fn lint_host(host: &HostConfig, default_interval: u64) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    let interval = host.interval.unwrap_or(default_interval);
+    if let Some(minimum) = providers::preset_min_interval(&host.provider) {
+        if interval < minimum {
+            warnings.push(format!(
+                "interval {}s is below the {} provider's documented minimum of {}s - using {}s",
+                interval, host.provider, minimum, minimum
+            ));
+        }
+    }
+
+    if host.ddns.starts_with("http://") || host.ddns.starts_with("https://") {
+        warnings.push(
+            "ddns should not include a scheme - updates are always sent over HTTPS".to_string(),
+        );
+    }
+
+    if let Some(oauth2) = &host.oauth2 {
+        if oauth2.token_url.starts_with("http://") {
+            warnings.push(
+                "oauth2.token_url uses plaintext HTTP - the client secret is sent unencrypted"
+                    .to_string(),
+            );
+        }
+    }
+
+    #[cfg(feature = "provider-custom")]
+    if let Some(custom) = &host.custom {
+        let sends_secret = std::iter::once(&custom.url)
+            .chain(custom.body.iter())
+            .chain(custom.headers.values())
+            .any(|template| template.contains("{password}") || template.contains("{token}"));
+        if custom.url.starts_with("http://") && sends_secret {
+            warnings.push(
+                "custom.url uses plaintext HTTP - the credentials are sent unencrypted".to_string(),
+            );
+        }
+    }
+
+    if let Some(ip) = host.ip {
+        let version = if ip.is_ipv4() {
+            IpVersion::Ipv4
+        } else {
+            IpVersion::Ipv6
+        };
+        if host.ip_version != version && host.ip_version != IpVersion::default() {
+            warnings.push(format!(
+                "ip {} is set - ip_version is ignored and only that record is updated",
+                ip
+            ));
+        }
+        if ip.is_ipv6() && !supports_ipv6(host) {
+            warnings.push(format!(
+                "ip {} is set but the {} provider has no AAAA records",
+                ip, host.provider
+            ));
+        }
+    } else if host.ip_version != IpVersion::Ipv4 && !supports_ipv6(host) {
+        warnings.push(format!(
+            "ip_version {} is set but the {} provider has no AAAA records",
+            format!("{:?}", host.ip_version).to_lowercase(),
+            host.provider
+        ));
+    }
+
+    match (host.ipv6_suffix, host.ipv6_prefix_length) {
+        (None, Some(_)) => warnings
+            .push("ipv6_prefix_length is set without ipv6_suffix - it is ignored".to_string()),
+        (Some(_), Some(length)) if length > 128 => warnings.push(format!(
+            "ipv6_prefix_length {} is longer than an IPv6 address - using 128",
+            length
+        )),
+        (Some(suffix), length) => {
+            let length = length.unwrap_or(64).min(128) as u32;
+            let mask = u128::MAX.checked_shl(128 - length).unwrap_or(0);
+            if u128::from(suffix) & mask != 0 {
+                warnings.push(format!(
+                    "ipv6_suffix {} has bits inside the /{} prefix, which are replaced by the detected prefix",
+                    suffix, length
+                ));
+            }
+            if host.ip_version == IpVersion::Ipv4 {
+                warnings.push(
+                    "ipv6_suffix is set but ip_version is ipv4 - no AAAA record is updated"
+                        .to_string(),
+                );
+            }
+        }
+        (None, None) => {}
+    }
+
+    for (name, value, file) in [
+        ("pass", &host.pass, &host.pass_file),
+        ("token", &host.token, &host.token_file),
+        ("secret", &host.secret, &host.secret_file),
+    ] {
+        if !value.is_empty() && !file.is_empty() {
+            warnings.push(format!(
+                "{} and {}_file are both set - the value from {}_file is used",
+                name, name, name
+            ));
+        }
+    }
+
+    if host.provider == "google" && host.user.contains('@') {
+        warnings.push(
+            "google provider needs the generated credentials of the record, not the account login"
+                .to_string(),
+        );
+    }
+
+    if is_placeholder(&host.user) {
+        warnings.push(format!("user looks like a placeholder: '{}'", host.user));
+    }
+    if is_placeholder(&host.pass) {
+        warnings.push("pass looks like a placeholder".to_string());
+    }
+    if is_placeholder(&host.token) {
+        warnings.push("token looks like a placeholder".to_string());
+    }
+    if is_placeholder(&host.secret) {
+        warnings.push("secret looks like a placeholder".to_string());
+    }
+    if is_placeholder(&host.ddns) {
+        warnings.push(format!("ddns looks like a placeholder: '{}'", host.ddns));
+    }
+
+    warnings
+}
+
+fn supports_ipv6(host: &HostConfig) -> bool {
+    providers::build(host).map_or(true, |p| p.supports_ipv6())
+}
+
+fn is_placeholder(value: &str) -> bool {
+    let value = value.to_lowercase();
+    value.starts_with("your")
+        || value.contains("example.com")
+        || PLACEHOLDERS.contains(&value.as_str())
+}
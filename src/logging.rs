@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set by --plain; the config can switch plain mode on but not off again.
+static FORCE_PLAIN: AtomicBool = AtomicBool::new(false);
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStyle {
+    #[default]
+    Fancy,
+    Plain,
+}
+
+pub fn init(force_plain: bool) {
+    FORCE_PLAIN.store(force_plain, Ordering::SeqCst);
+    PLAIN.store(force_plain, Ordering::SeqCst);
+
+    env_logger::Builder::from_env(env_logger::Env::new().default_filter_or("info"))
+        .format(|buf, record| {
+            let timestamp = buf.timestamp();
+            if PLAIN.load(Ordering::Relaxed) {
+                writeln!(
+                    buf,
+                    "[{} {:<5} {}] {}",
+                    timestamp,
+                    record.level(),
+                    record.target(),
+                    to_ascii(&record.args().to_string())
+                )
+            } else {
+                let style = buf.default_level_style(record.level());
+                writeln!(
+                    buf,
+                    "[{} {style}{:<5}{style:#} {}] {}",
+                    timestamp,
+                    record.level(),
+                    record.target(),
+                    record.args()
+                )
+            }
+        })
+        .init();
+}
+
+pub fn apply_style(style: LogStyle) {
+    let plain = FORCE_PLAIN.load(Ordering::SeqCst) || style == LogStyle::Plain;
+    PLAIN.store(plain, Ordering::SeqCst);
+}
+
+// Non-UTF-8 router consoles and some syslog pipelines mangle the status symbols,
+// so they are spelled out and anything else outside ASCII is replaced.
+fn to_ascii(message: &str) -> String {
+    message
+        .replace('✓', "[OK]")
+        .replace('✗', "[FAIL]")
+        .replace('⚠', "[WARN]")
+        .chars()
+        .map(|c| if c.is_ascii() { c } else { '?' })
+        .collect()
+}
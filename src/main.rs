@@ -1,21 +1,412 @@
+mod audit;
+mod detect;
+mod dns;
+mod environment;
+mod lease;
+mod lint;
+mod logging;
+mod oauth2;
+mod providers;
+#[cfg(feature = "listen")]
+mod server;
+mod transport;
+
+use audit::{AuditConfig, AuditEntry, AuditLog};
 use chrono::{DateTime, Local};
+use clap::{Parser, Subcommand};
+use detect::IpSourceConfig;
+use dns::{RecordCheck, VerifyConfig};
+use ipnet::IpNet;
+use lease::{CoordinationConfig, LeaseStatus};
 use log::{error, info, warn};
-use notify::{Config as NotifyConfig, RecommendedWatcher, RecursiveMode, Watcher};
+use logging::LogStyle;
+use notify::{Config as NotifyConfig, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use oauth2::OAuth2Config;
+use providers::{Provider, UpdateOutcome};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use serde_json::{Map, Value};
+#[cfg(feature = "listen")]
+use server::ListenConfig;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::fs;
 use tokio::sync::{mpsc, RwLock};
 use tokio::time::{interval, sleep};
+use transport::HttpConfig;
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_CONFIG_ERROR: i32 = 2;
+const EXIT_AUTH_FAILED: i32 = 3;
+const EXIT_PANIC: i32 = 4;
+
+// Whether fatal conditions end the process. Kept outside AppState so the panic
+// hook can read it.
+static EXIT_ON_FATAL: AtomicBool = AtomicBool::new(false);
+
+// The checker is restarted when no check cycle completed for this many intervals
+const WATCHDOG_MULTIPLE: u64 = 3;
+
+const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("GIT_HASH"),
+    ", built ",
+    env!("BUILD_DATE"),
+    ")\nfeatures: ",
+    env!("BUILD_FEATURES")
+);
+
+#[derive(Parser)]
+#[command(
+    version,
+    long_version = LONG_VERSION,
+    about = "Keeps a DDNS record pointed at your public IP"
+)]
+struct Cli {
+    /// Log plain ASCII without status symbols or colors
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// Exit with a distinct code on fatal errors instead of waiting for a fix
+    #[arg(long, global = true)]
+    exit_on_fatal: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a single update and exit instead of running as a daemon
+    Once {
+        /// Publish this IP instead of detecting it ("-" reads it from stdin)
+        #[arg(long, env = "DDNS_ONCE_IP")]
+        ip: Option<String>,
+    },
+    /// Validate the config and report risky settings, then exit
+    Check,
+    /// Print version and build information
+    Version,
+    /// Generate shell completions
+    #[cfg(feature = "completions")]
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct Config {
+    // A single host can be configured at the top level instead of in `hosts`
+    #[serde(flatten)]
+    host: HostConfig,
+    #[serde(default)]
+    hosts: Vec<HostConfig>,
+    #[serde(default = "default_interval")]
+    interval: u64,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    secret_refresh: u64,
+    #[serde(default)]
+    drift_check: u64,
+    // Looks up the live record before a host's first update, so a restart
+    // does not push an address the record already has. The authoritative
+    // servers see an update right away; the system resolver, which may answer
+    // from a stale cache, only takes over where port 53 is blocked.
+    #[serde(default)]
+    record_check: RecordCheck,
+    #[serde(default)]
+    verify_update: Option<VerifyConfig>,
+    // A changed address has to be detected this many times in a row, and for
+    // at least confirm_duration seconds, before it is published
+    #[serde(default)]
+    confirm_checks: u32,
+    #[serde(default)]
+    confirm_duration: u64,
+    #[serde(default)]
+    coordination: Option<CoordinationConfig>,
+    #[cfg(feature = "listen")]
+    #[serde(default)]
+    listen: Option<ListenConfig>,
+    #[serde(default)]
+    http: HttpConfig,
+    #[serde(default)]
+    log_style: LogStyle,
+    #[serde(default)]
+    exit_on_fatal: bool,
+    #[serde(default)]
+    watch: WatchConfig,
+    #[serde(default)]
+    private_ips: PrivateIpPolicy,
+    // URL fetched from a detected IPv6 address before it is published; an
+    // address that cannot reach it would break dual-stack clients
+    #[serde(default)]
+    ipv6_probe: String,
+    #[serde(default)]
+    ip_source: IpSourceConfig,
+    #[serde(default)]
+    audit_log: Option<AuditConfig>,
+}
+
+// What to do with a detected address that is not reachable from the internet,
+// e.g. a CGNAT or RFC 1918 address
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum PrivateIpPolicy {
+    #[default]
+    Skip,
+    Warn,
+    Allow,
+}
+
+// One DDNS record to keep updated, with its own credentials, provider and
+// optionally its own check interval.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+struct HostConfig {
+    #[serde(default)]
     user: String,
+    #[serde(default)]
     pass: String,
+    #[serde(default)]
+    token: String,
+    #[serde(default)]
+    secret: String,
+    // Files holding the pass, token or secret instead, e.g. a Docker or
+    // Kubernetes secret mount; read on every config load
+    #[serde(default)]
+    pass_file: String,
+    #[serde(default)]
+    token_file: String,
+    #[serde(default)]
+    secret_file: String,
+    #[serde(default)]
     ddns: String,
-    #[serde(default = "default_interval")]
-    interval: u64,
+    #[serde(default = "providers::default_name")]
+    provider: String,
+    #[serde(default)]
+    hostname: String,
+    #[serde(default)]
+    zone: String,
+    #[serde(default)]
+    ttl: Option<u32>,
+    #[serde(default)]
+    interval: Option<u64>,
+    #[serde(default)]
+    ip_version: IpVersion,
+    // Published instead of the detected address
+    #[serde(default)]
+    ip: Option<IpAddr>,
+    // Ranges the host's address has to (not) fall into
+    #[serde(default)]
+    allowed_ips: Vec<IpNet>,
+    #[serde(default)]
+    denied_ips: Vec<IpNet>,
+    // Host part of a LAN host's address inside the delegated prefix; the
+    // prefix itself comes from the detected address
+    #[serde(default)]
+    ipv6_suffix: Option<Ipv6Addr>,
+    #[serde(default)]
+    ipv6_prefix_length: Option<u8>,
+    #[serde(default)]
+    oauth2: Option<OAuth2Config>,
+    #[cfg(feature = "provider-dynv6")]
+    #[serde(default)]
+    dynv6: Option<providers::Dynv6Config>,
+    #[cfg(feature = "provider-njalla")]
+    #[serde(default)]
+    njalla: Option<providers::NjallaConfig>,
+    #[cfg(feature = "provider-ionos")]
+    #[serde(default)]
+    ionos: Option<providers::IonosConfig>,
+    #[cfg(feature = "provider-azure")]
+    #[serde(default)]
+    azure: Option<providers::AzureConfig>,
+    #[cfg(feature = "provider-oci")]
+    #[serde(default)]
+    oci: Option<providers::OciConfig>,
+    #[cfg(feature = "provider-rfc2136")]
+    #[serde(default)]
+    rfc2136: Option<providers::Rfc2136Config>,
+    #[cfg(feature = "provider-route53")]
+    #[serde(default)]
+    route53: Option<providers::Route53Config>,
+    #[cfg(feature = "provider-custom")]
+    #[serde(default)]
+    custom: Option<providers::CustomConfig>,
+}
+
+// Which records a host keeps updated: A, AAAA or both
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum IpVersion {
+    #[default]
+    Ipv4,
+    Ipv6,
+    Both,
+}
+
+impl IpVersion {
+    fn includes(&self, family: IpFamily) -> bool {
+        match self {
+            IpVersion::Ipv4 => family == IpFamily::V4,
+            IpVersion::Ipv6 => family == IpFamily::V6,
+            IpVersion::Both => true,
+        }
+    }
+
+    fn families(&self) -> Vec<IpFamily> {
+        IpFamily::ALL
+            .into_iter()
+            .filter(|family| self.includes(*family))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    const ALL: [IpFamily; 2] = [IpFamily::V4, IpFamily::V6];
+
+    fn of(ip: &IpAddr) -> Self {
+        if ip.is_ipv4() {
+            IpFamily::V4
+        } else {
+            IpFamily::V6
+        }
+    }
+
+    fn record_type(&self) -> &'static str {
+        match self {
+            IpFamily::V4 => "A",
+            IpFamily::V6 => "AAAA",
+        }
+    }
+
+    // Cloudflare DNS, reachable over either family
+    fn connectivity_url(&self) -> &'static str {
+        match self {
+            IpFamily::V4 => "https://1.1.1.1",
+            IpFamily::V6 => "https://[2606:4700:4700::1111]",
+        }
+    }
+}
+
+impl HostConfig {
+    fn is_valid(&self) -> bool {
+        providers::build(self).is_ok()
+    }
+
+    fn normalize(&mut self, default_interval: u64) {
+        let mut interval = self.interval.unwrap_or(default_interval);
+        if interval < 60 {
+            interval = 300;
+        }
+        if let Some(minimum) = providers::preset_min_interval(&self.provider) {
+            interval = interval.max(minimum);
+        }
+        self.interval = Some(interval);
+        if let Some(ip) = self.ip {
+            self.ip_version = match IpFamily::of(&ip) {
+                IpFamily::V4 => IpVersion::Ipv4,
+                IpFamily::V6 => IpVersion::Ipv6,
+            };
+        }
+    }
+
+    // Whether the host takes the address: a host with a fixed address only
+    // takes that one
+    fn takes(&self, ip: &IpAddr) -> bool {
+        match self.ip {
+            Some(fixed) => fixed == *ip,
+            None => self.ip_version.includes(IpFamily::of(ip)),
+        }
+    }
+
+    // Last line of defense against a wrong detection source, a VPN exit or a
+    // hijacked echo service steering the record somewhere else. allowed_ips
+    // only restricts the families it lists, so a host that takes both can
+    // limit one of them
+    fn accepts_ip(&self, ip: &IpAddr) -> bool {
+        let allowed = self
+            .allowed_ips
+            .iter()
+            .all(|net| IpFamily::of(&net.addr()) != IpFamily::of(ip))
+            || self.allowed_ips.iter().any(|net| net.contains(ip));
+        allowed && !self.denied_ips.iter().any(|net| net.contains(ip))
+    }
+
+    // The address the host's record gets for a detected one: with an
+    // ipv6_suffix, the detected address's prefix followed by the suffix
+    fn address_for(&self, ip: &IpAddr) -> IpAddr {
+        match (ip, self.ipv6_suffix) {
+            (IpAddr::V6(ip), Some(suffix)) => {
+                let length = self.ipv6_prefix_length.unwrap_or(64).min(128) as u32;
+                let mask = u128::MAX.checked_shl(128 - length).unwrap_or(0);
+                IpAddr::V6(Ipv6Addr::from(
+                    u128::from(*ip) & mask | u128::from(suffix) & !mask,
+                ))
+            }
+            _ => *ip,
+        }
+    }
+
+    fn interval(&self) -> u64 {
+        self.interval.unwrap_or_else(default_interval)
+    }
+
+    fn name(&self) -> &str {
+        if !self.hostname.is_empty() {
+            &self.hostname
+        } else if !self.ddns.is_empty() {
+            &self.ddns
+        } else {
+            &self.provider
+        }
+    }
+
+    // Identifies the host across reloads, so it stays the same when only the
+    // credentials change
+    fn key(&self) -> String {
+        format!("{}#{}", self.ddns, self.hostname)
+    }
+
+    // Identifies one of the host's A/AAAA records
+    fn record_key(&self, family: IpFamily) -> String {
+        format!("{}#{}", self.key(), family.record_type())
+    }
+}
+
+// Filesystem events never arrive on NFS mounts and some Docker volume drivers,
+// so the config watcher can poll for changes instead.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct WatchConfig {
+    #[serde(default)]
+    poll: bool,
+    #[serde(default = "default_poll_interval")]
+    poll_interval: u64,
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            poll: false,
+            poll_interval: default_poll_interval(),
+        }
+    }
 }
 
 fn default_interval() -> u64 {
@@ -23,34 +414,103 @@ fn default_interval() -> u64 {
 }
 
 impl Config {
+    // Turns the top-level host settings into the only entry of `hosts` when no
+    // hosts are listed, so the rest of the updater only deals with `hosts`.
+    fn expand_legacy_host(&mut self) {
+        if !self.hosts.is_empty() {
+            return;
+        }
+        self.hosts.push(std::mem::take(&mut self.host));
+    }
+
     fn is_valid(&self) -> bool {
-        !self.user.is_empty() && !self.pass.is_empty() && !self.ddns.is_empty()
+        #[cfg(feature = "listen")]
+        if self.listen.as_ref().is_some_and(|l| !l.is_valid()) {
+            return false;
+        }
+        !self.hosts.is_empty() && self.hosts.iter().all(HostConfig::is_valid)
     }
 
     fn normalize(&mut self) {
         if self.interval < 60 {
             self.interval = 300;
         }
+        for host in &mut self.hosts {
+            host.normalize(self.interval);
+        }
+        if self.secret_refresh > 0 && self.secret_refresh < 60 {
+            self.secret_refresh = 60;
+        }
+        if self.drift_check > 0 && self.drift_check < 300 {
+            self.drift_check = 300;
+        }
+        if let Some(coordination) = &mut self.coordination {
+            coordination.normalize();
+        }
+        if let Some(verify) = &mut self.verify_update {
+            verify.normalize();
+        }
+        if self.watch.poll_interval == 0 {
+            self.watch.poll_interval = default_poll_interval();
+        }
+    }
+
+    // The checker runs at the shortest host interval; hosts with a longer one
+    // are skipped until their own interval has passed.
+    fn check_interval(&self) -> u64 {
+        self.hosts
+            .iter()
+            .map(HostConfig::interval)
+            .min()
+            .unwrap_or(self.interval)
+    }
+
+    fn is_standby(&self, state: &AppState) -> bool {
+        self.coordination.is_some() && !state.leader.load(Ordering::SeqCst)
     }
 }
 
 struct AppState {
+    config_path: String,
+    force_exit_on_fatal: bool,
     config: Arc<RwLock<Option<Config>>>,
-    ip_cache: Arc<RwLock<Option<String>>>,
-    last_change_time: Arc<RwLock<Option<DateTime<Local>>>>,
-    client: reqwest::Client,
+    config_files: Arc<RwLock<Vec<PathBuf>>>,
+    config_warnings: Arc<RwLock<Vec<String>>>,
+    records: RwLock<HashMap<String, RecordState>>,
+    providers: RwLock<ProviderCache>,
+    // Hosts the provider blocked, with the settings they were blocked under
+    blocked: RwLock<HashMap<String, HostConfig>>,
+    // Changed addresses waiting for confirmation, by record type
+    pending: RwLock<HashMap<&'static str, PendingIp>>,
+    instance_id: String,
+    leader: AtomicBool,
+    last_cycle: Arc<RwLock<Instant>>,
+    audit: AuditLog,
+    client: RwLock<reqwest::Client>,
+    // Detection clients bound to IPv4 and IPv6, if http.detection.bind_family is set
+    family_clients: RwLock<Option<[reqwest::Client; 2]>>,
+    update_client: RwLock<reqwest::Client>,
 }
 
 impl AppState {
-    fn new() -> Self {
+    fn new(config_path: &str, force_exit_on_fatal: bool) -> Self {
         Self {
+            config_path: config_path.to_string(),
+            force_exit_on_fatal,
             config: Arc::new(RwLock::new(None)),
-            ip_cache: Arc::new(RwLock::new(None)),
-            last_change_time: Arc::new(RwLock::new(None)),
-            client: reqwest::Client::builder()
-                .timeout(Duration::from_secs(10))
-                .build()
-                .unwrap(),
+            config_files: Arc::new(RwLock::new(Vec::new())),
+            config_warnings: Arc::new(RwLock::new(Vec::new())),
+            records: RwLock::new(HashMap::new()),
+            providers: RwLock::new(HashMap::new()),
+            blocked: RwLock::new(HashMap::new()),
+            pending: RwLock::new(HashMap::new()),
+            instance_id: lease::default_instance_id(),
+            leader: AtomicBool::new(false),
+            last_cycle: Arc::new(RwLock::new(Instant::now())),
+            audit: AuditLog::new(),
+            client: RwLock::new(transport::build_client(&Default::default())),
+            family_clients: RwLock::new(None),
+            update_client: RwLock::new(transport::build_client(&Default::default())),
         }
     }
 }
@@ -62,112 +522,502 @@ enum ConfigLoadResult {
     NoChange,
 }
 
+#[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+compile_error!("enable either the `rustls` or the `native-tls` feature");
+
 #[tokio::main]
 async fn main() {
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    let cli = Cli::parse();
+    logging::init(cli.plain);
+    EXIT_ON_FATAL.store(cli.exit_on_fatal, Ordering::SeqCst);
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        exit_if_fatal(EXIT_PANIC);
+    }));
 
-    let state = Arc::new(AppState::new());
-    let config_path = "config/config.json";
+    let config_path = config_file_path();
+    let state = Arc::new(AppState::new(config_path, cli.exit_on_fatal));
+
+    match cli.command {
+        Some(Command::Once { ip }) => std::process::exit(run_once(state, ip).await),
+        Some(Command::Check) => std::process::exit(run_check(state).await),
+        Some(Command::Version) => {
+            println!("ddns-updater {}", LONG_VERSION);
+            return;
+        }
+        #[cfg(feature = "completions")]
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(
+                shell,
+                &mut <Cli as clap::CommandFactory>::command(),
+                "ddns-updater",
+                &mut std::io::stdout(),
+            );
+            return;
+        }
+        None => {}
+    }
+
+    info!("Starting ddns-updater {}", LONG_VERSION.replace('\n', "; "));
 
     // Load initial config
     match load_config(config_path, state.clone(), true).await {
         ConfigLoadResult::Success => {
-            tokio::spawn(start_ip_checker(state.clone()));
+            tokio::spawn(supervise_checker(state.clone()));
         }
         _ => {
-            error!("Failed to load initial config. Please fix config.json and restart.");
+            error!(
+                "Failed to load initial config. Please fix {} and restart.",
+                config_path
+            );
+            exit_if_fatal(EXIT_CONFIG_ERROR);
         }
     }
 
     // Watch config file
-    tokio::spawn(watch_config(config_path.to_string(), state.clone()));
+    if Path::new(config_path).exists() {
+        tokio::spawn(watch_config(config_path.to_string(), state.clone()));
+    } else {
+        info!(
+            "No config file at {} - using environment variables only",
+            config_path
+        );
+    }
+    tokio::spawn(refresh_secrets(state.clone()));
+    tokio::spawn(reconcile_drift(state.clone()));
+    tokio::spawn(maintain_lease(state.clone()));
+    tokio::spawn(watch_ip_files(state.clone()));
+    #[cfg(feature = "listen")]
+    tokio::spawn(server::run(state.clone()));
 
     // Keep main thread alive
     tokio::signal::ctrl_c().await.ok();
     info!("Shutting down...");
+
+    let coordination = state
+        .config
+        .read()
+        .await
+        .as_ref()
+        .and_then(|c| c.coordination.clone());
+    if let Some(coordination) = coordination {
+        let id = lease_holder_id(&state, &coordination);
+        if let Err(e) = lease::release(Path::new(&coordination.lock_file), &id).await {
+            warn!("Failed to release update lease: {}", e);
+        }
+    }
+}
+
+// Ends the process with the given code when exit_on_fatal is enabled.
+fn exit_if_fatal(code: i32) {
+    if EXIT_ON_FATAL.load(Ordering::SeqCst) {
+        error!("✗ Fatal error - exiting with code {}", code);
+        std::process::exit(code);
+    }
+}
+
+// One-shot mode for ip-up hooks and router scripts. Returns the process exit code.
+async fn run_once(state: Arc<AppState>, ip: Option<String>) -> i32 {
+    if !matches!(
+        load_config(&state.config_path, state.clone(), true).await,
+        ConfigLoadResult::Success
+    ) {
+        return EXIT_CONFIG_ERROR;
+    }
+
+    let ip = match ip.as_deref() {
+        Some("-") => {
+            let mut line = String::new();
+            if let Err(e) = std::io::stdin().lock().read_line(&mut line) {
+                error!("✗ Failed to read IP from stdin: {}", e);
+                return EXIT_FAILURE;
+            }
+            line.trim().to_string()
+        }
+        Some(ip) => ip.trim().to_string(),
+        None => String::new(),
+    };
+
+    if !ip.is_empty() && ip.parse::<IpAddr>().is_err() {
+        error!("✗ Invalid IP address: '{}'", ip);
+        return EXIT_FAILURE;
+    }
+
+    // A one-shot run is triggered explicitly, so it always pushes
+    state.leader.store(true, Ordering::SeqCst);
+
+    let Some(config) = state.config.read().await.clone() else {
+        return EXIT_CONFIG_ERROR;
+    };
+    let outcome = if ip.is_empty() {
+        detect_and_publish(&state, &config, &config.hosts).await
+    } else {
+        // Hosts with a fixed address do not take the given one, but a hook
+        // run is still their chance to be published
+        let fixed = publish_fixed(&state, &config, &config.hosts).await;
+        let given = publish_to(&state, &config, &config.hosts, &ip).await;
+        if fixed > given {
+            fixed
+        } else {
+            given
+        }
+    };
+
+    match outcome {
+        PublishOutcome::Failed => EXIT_FAILURE,
+        PublishOutcome::AuthFailed => EXIT_AUTH_FAILED,
+        _ => 0,
+    }
+}
+
+async fn run_check(state: Arc<AppState>) -> i32 {
+    if !matches!(
+        load_config(&state.config_path, state.clone(), true).await,
+        ConfigLoadResult::Success
+    ) {
+        return EXIT_CONFIG_ERROR;
+    }
+
+    let warnings = state.config_warnings.read().await.len();
+    if warnings == 0 {
+        info!("✓ Config is valid");
+    } else {
+        warn!("⚠ Config is valid but has {} warning(s)", warnings);
+    }
+    0
 }
 
 async fn load_config(path: &str, state: Arc<AppState>, first_load: bool) -> ConfigLoadResult {
-    match fs::read_to_string(path).await {
-        Ok(contents) => match serde_json::from_str::<Config>(&contents) {
-            Ok(mut new_config) => {
-                new_config.normalize();
-
-                if !new_config.is_valid() {
-                    error!("✗ Invalid config: user, pass, or ddns is missing!");
-                    error!("Current config:");
-                    error!(
-                        "  - user: '{}'",
-                        if new_config.user.is_empty() {
-                            "<empty>"
-                        } else {
-                            &new_config.user
-                        }
-                    );
-                    error!(
-                        "  - pass: '{}'",
-                        if new_config.pass.is_empty() {
-                            "<empty>"
-                        } else {
-                            "<set>"
-                        }
-                    );
-                    error!(
-                        "  - ddns: '{}'",
-                        if new_config.ddns.is_empty() {
-                            "<empty>"
-                        } else {
-                            &new_config.ddns
-                        }
-                    );
-                    return ConfigLoadResult::InvalidConfig;
-                }
+    let (merged, files) = match read_config_files(Path::new(path)).await {
+        Ok(result) => result,
+        Err(result) => return result,
+    };
 
-                let mut config_guard = state.config.write().await;
-                let config_changed = config_guard.as_ref() != Some(&new_config);
+    match serde_json::from_value::<Config>(merged) {
+        Ok(mut new_config) => {
+            new_config.expand_legacy_host();
+            let warnings = lint::lint(&new_config);
+            if let Err(e) = read_secret_files(&mut new_config, Path::new(path)).await {
+                error!("✗ Secret File Error: {}", e);
+                return ConfigLoadResult::InvalidConfig;
+            }
+            new_config.normalize();
 
-                if first_load {
-                    *config_guard = Some(new_config.clone());
-                    info!("✓ Config loaded successfully");
-                    return ConfigLoadResult::Success;
+            if !new_config.is_valid() {
+                error!("✗ Invalid config: required settings are missing!");
+                if new_config.hosts.is_empty() {
+                    error!("  - hosts: <empty>");
                 }
+                for (index, host) in new_config.hosts.iter().enumerate() {
+                    if host.is_valid() {
+                        continue;
+                    }
+                    if new_config.hosts.len() == 1 {
+                        error!("Current config:");
+                    } else {
+                        error!("Host {} ({}):", index + 1, host.name());
+                    }
+                    log_host_config(host);
+                }
+                return ConfigLoadResult::InvalidConfig;
+            }
 
-                if config_changed {
-                    *config_guard = Some(new_config.clone());
-                    info!("✓ Config changed and reloaded");
-                    return ConfigLoadResult::Success;
+            *state.config_files.write().await = files;
+            logging::apply_style(new_config.log_style);
+            EXIT_ON_FATAL.store(
+                state.force_exit_on_fatal || new_config.exit_on_fatal,
+                Ordering::SeqCst,
+            );
+
+            let mut config_guard = state.config.write().await;
+            let config_changed = config_guard.as_ref() != Some(&new_config);
+
+            if first_load || config_changed {
+                for warning in &warnings {
+                    warn!("⚠ Config warning: {}", warning);
                 }
+                *state.config_warnings.write().await = warnings;
+            }
 
-                ConfigLoadResult::NoChange
+            if config_guard.as_ref().map(|c| &c.http) != Some(&new_config.http) {
+                *state.client.write().await = transport::build_client(&new_config.http.detection);
+                *state.family_clients.write().await =
+                    transport::build_family_clients(&new_config.http.detection);
+                *state.update_client.write().await =
+                    transport::build_client(&new_config.http.update);
             }
-            Err(e) => {
-                error!("✗ JSON Parse Error: {}", e);
-                error!("File: {}", path);
-                error!("Please check your JSON syntax (commas, quotes, brackets)");
-                ConfigLoadResult::InvalidConfig
+
+            if first_load {
+                *config_guard = Some(new_config.clone());
+                info!("✓ Config loaded successfully");
+                return ConfigLoadResult::Success;
+            }
+
+            if config_changed {
+                *config_guard = Some(new_config.clone());
+                info!("✓ Config changed and reloaded");
+                return ConfigLoadResult::Success;
+            }
+
+            ConfigLoadResult::NoChange
+        }
+        Err(e) => {
+            error!("✗ Config Error: {}", e);
+            error!("File: {}", path);
+            ConfigLoadResult::InvalidConfig
+        }
+    }
+}
+
+fn log_host_config(host: &HostConfig) {
+    error!(
+        "  - user: '{}'",
+        if host.user.is_empty() {
+            "<empty>"
+        } else {
+            &host.user
+        }
+    );
+    error!(
+        "  - pass: '{}'",
+        if host.pass.is_empty() {
+            "<empty>"
+        } else {
+            "<set>"
+        }
+    );
+    error!(
+        "  - ddns: '{}'",
+        if host.ddns.is_empty() {
+            "<empty>"
+        } else {
+            &host.ddns
+        }
+    );
+    if let Err(e) = providers::build(host) {
+        error!("  - {}", e);
+    }
+}
+
+// Reads the main config and merges its includes. Included files are applied in
+// the listed order (later files win), keys in the main file override them, and
+// DDNS_* environment variables override everything. Without a config file the
+// environment variables alone make up the config.
+async fn read_config_files(path: &Path) -> Result<(Value, Vec<PathBuf>), ConfigLoadResult> {
+    let main_config = if !path.exists() && environment::is_configured() {
+        Map::new()
+    } else {
+        read_config_file(path).await?
+    };
+    let mut files = vec![path.to_path_buf()];
+
+    let includes = match main_config.get("include") {
+        None => Vec::new(),
+        Some(value) => match serde_json::from_value::<Vec<String>>(value.clone()) {
+            Ok(includes) => includes,
+            Err(_) => {
+                error!("✗ Invalid config: include must be a list of file paths");
+                error!("File: {}", path.display());
+                return Err(ConfigLoadResult::InvalidConfig);
             }
         },
+    };
+
+    let mut merged = Map::new();
+    for include in includes {
+        let include_path = resolve_include(path, &include);
+        let mut included = read_config_file(&include_path).await?;
+        if included.remove("include").is_some() {
+            warn!(
+                "⚠ Nested include in {} is not supported and was ignored",
+                include_path.display()
+            );
+        }
+        merged.extend(included);
+        files.push(include_path);
+    }
+    merged.extend(main_config);
+
+    if let Err(e) = environment::apply(&mut merged) {
+        error!("✗ Environment Error: {}", e);
+        return Err(ConfigLoadResult::InvalidConfig);
+    }
+
+    Ok((Value::Object(merged), files))
+}
+
+// config.json, unless only a config file in another supported format exists
+fn config_file_path() -> &'static str {
+    const CANDIDATES: &[&str] = &[
+        "config/config.json",
+        #[cfg(feature = "toml")]
+        "config/config.toml",
+        #[cfg(feature = "yaml")]
+        "config/config.yaml",
+        #[cfg(feature = "yaml")]
+        "config/config.yml",
+    ];
+    CANDIDATES
+        .iter()
+        .copied()
+        .find(|path| Path::new(path).exists())
+        .unwrap_or(CANDIDATES[0])
+}
+
+// The format is picked by the file extension; anything unknown is read as JSON
+async fn read_config_file(path: &Path) -> Result<Map<String, Value>, ConfigLoadResult> {
+    let contents = match fs::read_to_string(path).await {
+        Ok(contents) => contents,
         Err(e) => {
             error!("✗ File Read Error: {}", e);
-            error!("File: {}", path);
-            ConfigLoadResult::FileError
+            error!("File: {}", path.display());
+            return Err(ConfigLoadResult::FileError);
+        }
+    };
+
+    let (format, parsed) = match path.extension().and_then(|ext| ext.to_str()) {
+        #[cfg(feature = "toml")]
+        Some("toml") => (
+            "TOML",
+            toml::from_str::<Value>(&contents).map_err(|e| {
+                let message = e.message().split_whitespace().collect::<Vec<_>>().join(" ");
+                match e.span() {
+                    Some(span) => format!(
+                        "{} at line {}",
+                        message,
+                        contents[..span.start].matches('\n').count() + 1
+                    ),
+                    None => message,
+                }
+            }),
+        ),
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => (
+            "YAML",
+            serde_yaml::from_str::<Value>(&contents).map_err(|e| e.to_string()),
+        ),
+        _ => (
+            "JSON",
+            serde_json::from_str::<Value>(&contents).map_err(|e| e.to_string()),
+        ),
+    };
+    match parsed {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => {
+            error!(
+                "✗ {} Parse Error: expected an object at the top level",
+                format
+            );
+            error!("File: {}", path.display());
+            Err(ConfigLoadResult::InvalidConfig)
+        }
+        Err(e) => {
+            error!("✗ {} Parse Error: {}", format, e);
+            error!("File: {}", path.display());
+            if format == "JSON" {
+                error!("Please check your JSON syntax (commas, quotes, brackets)");
+            }
+            Err(ConfigLoadResult::InvalidConfig)
+        }
+    }
+}
+
+// Relative paths are resolved against the directory of the main config file,
+// like includes. A trailing newline is not part of the secret.
+async fn read_secret_files(config: &mut Config, config_path: &Path) -> Result<(), String> {
+    for host in &mut config.hosts {
+        for (file, value) in [
+            (&host.pass_file, &mut host.pass),
+            (&host.token_file, &mut host.token),
+            (&host.secret_file, &mut host.secret),
+        ] {
+            if file.is_empty() {
+                continue;
+            }
+            let path = resolve_include(config_path, file);
+            let contents = fs::read_to_string(&path)
+                .await
+                .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+            *value = contents.trim_end_matches(['\r', '\n']).to_string();
         }
     }
+    Ok(())
+}
+
+fn resolve_include(config_path: &Path, include: &str) -> PathBuf {
+    let include = Path::new(include);
+    if include.is_absolute() {
+        include.to_path_buf()
+    } else {
+        config_path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(include)
+    }
 }
 
 async fn watch_config(config_path: String, state: Arc<AppState>) {
+    loop {
+        let settings = watch_settings(&state).await;
+        run_watcher(&config_path, &state, &settings).await;
+        info!("Watcher settings changed, restarting config watcher");
+    }
+}
+
+async fn watch_settings(state: &AppState) -> WatchConfig {
+    state
+        .config
+        .read()
+        .await
+        .as_ref()
+        .map(|c| c.watch.clone())
+        .unwrap_or_default()
+}
+
+type WatchEvent = notify::Result<notify::Event>;
+
+fn create_watcher(
+    settings: &WatchConfig,
+    tx: mpsc::Sender<WatchEvent>,
+) -> notify::Result<Box<dyn Watcher + Send>> {
+    let handler = move |res| {
+        tx.blocking_send(res).ok();
+    };
+
+    let poll_config =
+        NotifyConfig::default().with_poll_interval(Duration::from_secs(settings.poll_interval));
+
+    if settings.poll {
+        info!(
+            "Polling config files for changes every {}s",
+            settings.poll_interval
+        );
+        return Ok(Box::new(PollWatcher::new(handler, poll_config)?));
+    }
+
+    let fallback = handler.clone();
+    match RecommendedWatcher::new(handler, NotifyConfig::default()) {
+        Ok(watcher) => Ok(Box::new(watcher)),
+        Err(e) => {
+            warn!(
+                "⚠ Native file watching unavailable ({}) - falling back to polling every {}s",
+                e, settings.poll_interval
+            );
+            Ok(Box::new(PollWatcher::new(fallback, poll_config)?))
+        }
+    }
+}
+
+// Watches the config files until a reload changes the watcher settings.
+async fn run_watcher(config_path: &str, state: &Arc<AppState>, settings: &WatchConfig) {
     let (tx, mut rx) = mpsc::channel(1);
 
-    let mut watcher = RecommendedWatcher::new(
-        move |res| {
-            tx.blocking_send(res).ok();
-        },
-        NotifyConfig::default(),
-    )
-    .expect("Failed to create watcher");
+    let mut watcher = create_watcher(settings, tx).expect("Failed to create watcher");
 
     loop {
-        match watcher.watch(Path::new(&config_path), RecursiveMode::NonRecursive) {
+        match watcher.watch(Path::new(config_path), RecursiveMode::NonRecursive) {
             Ok(_) => {
                 info!("Watching config file for changes...");
                 break;
@@ -179,14 +1029,21 @@ async fn watch_config(config_path: String, state: Arc<AppState>) {
         }
     }
 
+    let mut watched = vec![PathBuf::from(config_path)];
+    sync_watched_files(watcher.as_mut(), &mut watched, state).await;
+
     while let Some(event) = rx.recv().await {
         match event {
             Ok(event) => {
                 if event.kind.is_modify() {
-                    match load_config(&config_path, state.clone(), false).await {
+                    match load_config(config_path, state.clone(), false).await {
                         ConfigLoadResult::Success => {
                             info!("✓ Config reloaded successfully");
                             tokio::spawn(check_and_update_ip(state.clone()));
+                            if watch_settings(state).await != *settings {
+                                return;
+                            }
+                            sync_watched_files(watcher.as_mut(), &mut watched, state).await;
                         }
                         ConfigLoadResult::InvalidConfig => {
                             warn!("✗ Config has validation errors - keeping previous valid config");
@@ -206,24 +1063,365 @@ async fn watch_config(config_path: String, state: Arc<AppState>) {
     }
 }
 
-async fn start_ip_checker(state: Arc<AppState>) {
-    loop {
-        let config = {
-            let config_guard = state.config.read().await;
-            match config_guard.clone() {
-                Some(c) => c,
-                None => {
-                    sleep(Duration::from_secs(5)).await;
-                    continue;
-                }
-            }
-        };
+// Keeps the watcher in line with the currently included files. The main config
+// file is always first in the list and stays watched.
+async fn sync_watched_files(
+    watcher: &mut (dyn Watcher + Send),
+    watched: &mut Vec<PathBuf>,
+    state: &AppState,
+) {
+    let files = state.config_files.read().await.clone();
+    let main_config = watched[0].clone();
 
-        let check_interval = Duration::from_secs(config.interval);
-        let mut ticker = interval(check_interval);
+    watched.retain(|path| {
+        if *path == main_config || files.contains(path) {
+            return true;
+        }
+        watcher.unwatch(path).ok();
+        false
+    });
 
-        // Initial check
-        check_and_update_ip(state.clone()).await;
+    for path in files.into_iter().skip(1) {
+        if watched.contains(&path) {
+            continue;
+        }
+        match watcher.watch(&path, RecursiveMode::NonRecursive) {
+            Ok(_) => {
+                info!("Watching included config {} for changes...", path.display());
+                watched.push(path);
+            }
+            Err(e) => warn!("Failed to watch included config {}: {}", path.display(), e),
+        }
+    }
+}
+
+// Checks the public address as soon as a file read by a file IP source changes,
+// instead of at the next interval
+async fn watch_ip_files(state: Arc<AppState>) {
+    loop {
+        let (files, settings) = ip_file_settings(&state).await;
+        if files.is_empty() {
+            sleep(Duration::from_secs(30)).await;
+            continue;
+        }
+        run_ip_file_watcher(&state, &files, &settings).await;
+    }
+}
+
+async fn ip_file_settings(state: &AppState) -> (Vec<PathBuf>, WatchConfig) {
+    state
+        .config
+        .read()
+        .await
+        .as_ref()
+        .map(|c| (c.ip_source.files(), c.watch.clone()))
+        .unwrap_or_default()
+}
+
+// Runs until the config changes the watched files or watcher settings. The
+// directories are watched rather than the files, since tools often replace
+// the file by renaming a new one over it.
+async fn run_ip_file_watcher(state: &Arc<AppState>, files: &[PathBuf], settings: &WatchConfig) {
+    let (tx, mut rx) = mpsc::channel(1);
+    let mut watcher = match create_watcher(settings, tx) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!("Failed to watch IP source files: {}", e);
+            sleep(Duration::from_secs(30)).await;
+            return;
+        }
+    };
+
+    let mut targets = Vec::new();
+    for file in files {
+        let dir = match file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let (Ok(dir), Some(name)) = (dir.canonicalize(), file.file_name()) else {
+            warn!("Cannot watch IP source file {}", file.display());
+            continue;
+        };
+        match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            Ok(_) => {
+                info!("Watching IP source file {} for changes...", file.display());
+                targets.push(dir.join(name));
+            }
+            Err(e) => warn!("Failed to watch IP source file {}: {}", file.display(), e),
+        }
+    }
+
+    let mut recheck = interval(Duration::from_secs(30));
+    recheck.tick().await;
+    loop {
+        tokio::select! {
+            Some(event) = rx.recv() => {
+                let Ok(event) = event else { continue };
+                if event.kind.is_access() || !event.paths.iter().any(|p| targets.contains(p)) {
+                    continue;
+                }
+                // A write often comes as several events; let the file settle
+                sleep(Duration::from_secs(1)).await;
+                while rx.try_recv().is_ok() {}
+
+                info!("IP source file changed - checking public IP");
+                let Some(config) = state.config.read().await.clone() else {
+                    continue;
+                };
+                #[cfg(feature = "listen")]
+                if config.listen.is_some() {
+                    continue;
+                }
+                if let PublishOutcome::AuthFailed =
+                    detect_and_publish(state, &config, &config.hosts).await
+                {
+                    exit_if_fatal(EXIT_AUTH_FAILED);
+                }
+            }
+            _ = recheck.tick() => {
+                if ip_file_settings(state).await != (files.to_vec(), settings.clone()) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// Re-reads the config (and its includes) on a fixed schedule. Secret mounts such
+// as Kubernetes secrets are swapped via symlinks, which never shows up as a
+// modify event on the watched file, so rotated credentials would go unnoticed.
+async fn refresh_secrets(state: Arc<AppState>) {
+    loop {
+        let refresh = state
+            .config
+            .read()
+            .await
+            .as_ref()
+            .map_or(0, |c| c.secret_refresh);
+
+        if refresh == 0 {
+            sleep(Duration::from_secs(60)).await;
+            continue;
+        }
+
+        sleep(Duration::from_secs(refresh)).await;
+        if let ConfigLoadResult::Success =
+            load_config(&state.config_path, state.clone(), false).await
+        {
+            info!("✓ Config re-read picked up new values");
+            tokio::spawn(check_and_update_ip(state.clone()));
+        }
+    }
+}
+
+// Resolves the managed hostnames and re-publishes the last IP of every host
+// whose record no longer matches it, e.g. after an edit in the provider
+// dashboard or a zone restore.
+async fn reconcile_drift(state: Arc<AppState>) {
+    loop {
+        let drift_check = state
+            .config
+            .read()
+            .await
+            .as_ref()
+            .map_or(0, |c| c.drift_check);
+
+        if drift_check == 0 {
+            sleep(Duration::from_secs(60)).await;
+            continue;
+        }
+
+        sleep(Duration::from_secs(drift_check)).await;
+
+        let Some(config) = state.config.read().await.clone() else {
+            continue;
+        };
+        if config.is_standby(&state) {
+            continue;
+        }
+
+        for host in config.hosts.iter().filter(|h| !h.hostname.is_empty()) {
+            for family in host.ip_version.families() {
+                check_drift(&state, &config, host, family, drift_check).await;
+            }
+        }
+    }
+}
+
+async fn check_drift(
+    state: &Arc<AppState>,
+    config: &Config,
+    host: &HostConfig,
+    family: IpFamily,
+    drift_check: u64,
+) {
+    let (ip, last_change) = match state.records.read().await.get(&host.record_key(family)) {
+        Some(record) => (record.ip.clone(), record.last_change),
+        None => return,
+    };
+    let Some(ip) = ip else {
+        return;
+    };
+    let Ok(published) = ip.parse::<IpAddr>() else {
+        return;
+    };
+
+    // Resolvers may still serve the old record for a while after an update
+    if last_change.is_some_and(|time| (Local::now() - time).num_seconds() < drift_check as i64) {
+        return;
+    }
+
+    let resolved: Vec<IpAddr> = match tokio::net::lookup_host((host.hostname.as_str(), 0)).await {
+        Ok(addrs) => addrs
+            .map(|addr| addr.ip())
+            .filter(|addr| addr.is_ipv4() == published.is_ipv4())
+            .collect(),
+        Err(e) => {
+            warn!("⚠ Drift check: cannot resolve {}: {}", host.hostname, e);
+            return;
+        }
+    };
+
+    if resolved.contains(&published) {
+        return;
+    }
+
+    warn!(
+        "⚠ DNS drift: {} resolves to {:?} but {} was published",
+        host.hostname, resolved, ip
+    );
+    if let PublishOutcome::Updated = publish_host(state, config, host, &published, true).await {
+        info!(
+            "✓ Drift corrected: {} points to {} again",
+            host.hostname, ip
+        );
+    }
+}
+
+fn lease_holder_id(state: &AppState, coordination: &CoordinationConfig) -> String {
+    coordination
+        .instance_id
+        .clone()
+        .unwrap_or_else(|| state.instance_id.clone())
+}
+
+// Holds or waits for the update lease when several instances share a lock file.
+// Only the lease holder pushes updates; standbys keep detecting the IP so they
+// can take over as soon as the active instance stops renewing.
+async fn maintain_lease(state: Arc<AppState>) {
+    let mut active: Option<bool> = None;
+
+    loop {
+        let coordination = state
+            .config
+            .read()
+            .await
+            .as_ref()
+            .and_then(|c| c.coordination.clone());
+        let Some(coordination) = coordination else {
+            state.leader.store(false, Ordering::SeqCst);
+            active = None;
+            sleep(Duration::from_secs(10)).await;
+            continue;
+        };
+
+        let id = lease_holder_id(&state, &coordination);
+        let lock_file = Path::new(&coordination.lock_file);
+        let leader = match lease::try_acquire(lock_file, &id, coordination.lease).await {
+            Ok(LeaseStatus::Held) => {
+                if active != Some(true) {
+                    info!(
+                        "✓ Acquired update lease as {} - this instance is active",
+                        id
+                    );
+                }
+                true
+            }
+            Ok(LeaseStatus::HeldBy(holder)) => {
+                if active != Some(false) {
+                    info!("Update lease held by {} - this instance is standby", holder);
+                }
+                false
+            }
+            Err(e) => {
+                error!("✗ Cannot access lock file {}: {}", lock_file.display(), e);
+                if active == Some(true) {
+                    warn!("⚠ Stepping down to standby until the lock file is reachable");
+                }
+                false
+            }
+        };
+
+        state.leader.store(leader, Ordering::SeqCst);
+        if leader && active != Some(true) {
+            tokio::spawn(check_and_update_ip(state.clone()));
+        }
+        active = Some(leader);
+
+        sleep(Duration::from_secs(coordination.lease / 3)).await;
+    }
+}
+
+// Restarts the IP checker when it dies or stops completing check cycles, e.g.
+// because a request hangs or the task panicked.
+async fn supervise_checker(state: Arc<AppState>) {
+    loop {
+        *state.last_cycle.write().await = Instant::now();
+        let mut checker = tokio::spawn(start_ip_checker(state.clone()));
+
+        loop {
+            tokio::select! {
+                result = &mut checker => {
+                    match result {
+                        Err(e) => error!("✗ Watchdog: IP checker died ({}) - restarting", e),
+                        Ok(()) => error!("✗ Watchdog: IP checker stopped - restarting"),
+                    }
+                    break;
+                }
+                _ = sleep(Duration::from_secs(30)) => {}
+            }
+
+            let Some(interval) = state
+                .config
+                .read()
+                .await
+                .as_ref()
+                .map(|c| c.check_interval())
+            else {
+                continue;
+            };
+            let stalled = state.last_cycle.read().await.elapsed();
+            if stalled > Duration::from_secs(interval * WATCHDOG_MULTIPLE) {
+                error!(
+                    "✗ Watchdog: no IP check completed for {}s - restarting IP checker",
+                    stalled.as_secs()
+                );
+                checker.abort();
+                break;
+            }
+        }
+    }
+}
+
+async fn start_ip_checker(state: Arc<AppState>) {
+    loop {
+        let config = {
+            let config_guard = state.config.read().await;
+            match config_guard.clone() {
+                Some(c) => c,
+                None => {
+                    sleep(Duration::from_secs(5)).await;
+                    continue;
+                }
+            }
+        };
+
+        let check_interval = Duration::from_secs(config.check_interval());
+        let mut ticker = interval(check_interval);
+
+        // Initial check
+        check_and_update_ip(state.clone()).await;
+        *state.last_cycle.write().await = Instant::now();
 
         loop {
             ticker.tick().await;
@@ -236,85 +1434,723 @@ async fn start_ip_checker(state: Arc<AppState>) {
             }
 
             check_and_update_ip(state.clone()).await;
+            *state.last_cycle.write().await = Instant::now();
         }
     }
 }
 
 async fn check_and_update_ip(state: Arc<AppState>) {
-    // First check if we have internet connectivity
-    if let Err(e) = check_internet_connectivity(&state.client).await {
-        error!("✗ No internet connection: {}", e);
+    let Some(config) = state.config.read().await.clone() else {
         return;
+    };
+    let hosts = due_hosts(&state, &config).await;
+    if hosts.is_empty() {
+        return;
+    }
+
+    // In listen mode the IP is pushed to us by the router instead
+    #[cfg(feature = "listen")]
+    if config.listen.is_some() {
+        if let PublishOutcome::AuthFailed = publish_fixed(&state, &config, &hosts).await {
+            exit_if_fatal(EXIT_AUTH_FAILED);
+        }
+        return;
+    }
+
+    if let PublishOutcome::AuthFailed = detect_and_publish(&state, &config, &hosts).await {
+        exit_if_fatal(EXIT_AUTH_FAILED);
+    }
+}
+
+// Detects the public address of every family the hosts need and publishes it
+// to the hosts that take it. Hosts with a fixed address get that one instead.
+// Returns the most severe outcome.
+async fn detect_and_publish(
+    state: &Arc<AppState>,
+    config: &Config,
+    hosts: &[HostConfig],
+) -> PublishOutcome {
+    let mut outcome = publish_fixed(state, config, hosts).await;
+    for family in IpFamily::ALL {
+        if !hosts
+            .iter()
+            .any(|h| h.ip.is_none() && h.ip_version.includes(family))
+        {
+            continue;
+        }
+        let result = match detect_ip(state, config, family).await {
+            Some(ip) if !confirm_change(state, config, hosts, family, &ip).await => {
+                PublishOutcome::Unchanged
+            }
+            Some(ip) => publish_to(state, config, hosts, &ip).await,
+            None => PublishOutcome::Failed,
+        };
+        if result > outcome {
+            outcome = result;
+        }
+    }
+    outcome
+}
+
+async fn publish_fixed(
+    state: &Arc<AppState>,
+    config: &Config,
+    hosts: &[HostConfig],
+) -> PublishOutcome {
+    let mut outcome = PublishOutcome::Unchanged;
+    for host in hosts {
+        let Some(ip) = host.ip else {
+            continue;
+        };
+        let result = publish_to(state, config, std::slice::from_ref(host), &ip.to_string()).await;
+        if result > outcome {
+            outcome = result;
+        }
+    }
+    outcome
+}
+
+struct PendingIp {
+    ip: String,
+    checks: u32,
+    since: Instant,
+}
+
+// Holds back a detected address that differs from the published one until it
+// was seen often and long enough, so an address that flaps during a re-dial is
+// not published. Addresses without a published one to replace pass right away.
+async fn confirm_change(
+    state: &AppState,
+    config: &Config,
+    hosts: &[HostConfig],
+    family: IpFamily,
+    ip: &str,
+) -> bool {
+    if config.confirm_checks <= 1 && config.confirm_duration == 0 {
+        return true;
+    }
+
+    let changed = {
+        let records = state.records.read().await;
+        hosts
+            .iter()
+            .filter(|h| h.ip.is_none() && h.ip_version.includes(family))
+            .any(|h| {
+                records
+                    .get(&h.record_key(family))
+                    .and_then(|r| r.ip.as_deref())
+                    .is_some_and(|published| published != ip)
+            })
+    };
+    let mut pending = state.pending.write().await;
+    if !changed {
+        pending.remove(family.record_type());
+        return true;
     }
 
-    let ip = match get_public_ip(&state.client).await {
-        Ok(ip) => ip,
+    let entry = pending
+        .entry(family.record_type())
+        .or_insert_with(|| PendingIp {
+            ip: ip.to_string(),
+            checks: 0,
+            since: Instant::now(),
+        });
+    if entry.ip != ip {
+        *entry = PendingIp {
+            ip: ip.to_string(),
+            checks: 0,
+            since: Instant::now(),
+        };
+    }
+    entry.checks += 1;
+
+    let waited = entry.since.elapsed();
+    if entry.checks >= config.confirm_checks
+        && waited >= Duration::from_secs(config.confirm_duration)
+    {
+        pending.remove(family.record_type());
+        return true;
+    }
+    info!(
+        "IP change to {} seen {} time(s) over {}s - waiting for confirmation",
+        ip,
+        entry.checks,
+        waited.as_secs()
+    );
+    false
+}
+
+// Hosts whose own interval has passed since their last update attempt. The
+// tolerance keeps ticker jitter from pushing a host to the following cycle.
+async fn due_hosts(state: &AppState, config: &Config) -> Vec<HostConfig> {
+    let tolerance = Duration::from_secs(5);
+    let records = state.records.read().await;
+    config
+        .hosts
+        .iter()
+        .filter(|host| {
+            host.ip_version.families().into_iter().any(|family| {
+                match records
+                    .get(&host.record_key(family))
+                    .and_then(|s| s.last_attempt)
+                {
+                    Some(last) => {
+                        last.elapsed() + tolerance >= Duration::from_secs(host.interval())
+                    }
+                    None => true,
+                }
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+async fn detect_ip(state: &AppState, config: &Config, family: IpFamily) -> Option<String> {
+    let source = config.ip_source.source(family);
+    let client = match state.family_clients.read().await.as_ref() {
+        Some([v4, v6]) => match family {
+            IpFamily::V4 => v4.clone(),
+            IpFamily::V6 => v6.clone(),
+        },
+        None => state.client.read().await.clone(),
+    };
+    // First check if we have internet connectivity, for sources that need it
+    if source.uses_internet() {
+        if let Err(e) = check_internet_connectivity(&client, family).await {
+            error!("✗ No internet connection: {}", e);
+            return None;
+        }
+    }
+
+    match detect::detect(source, &client, family).await {
+        Ok(ip) => Some(ip.to_string()),
         Err(e) => {
             error!("✗ Failed to get public IP: {}", e);
-            if e.to_string().contains("dns")
-                || e.to_string().contains("connect")
-                || e.to_string().contains("timeout")
-            {
+            if e.contains("dns") || e.contains("connect") || e.contains("timeout") {
                 error!("⚠ Network issue detected - will retry at next interval");
             }
-            return;
+            None
         }
+    }
+}
+
+// Ordered by severity; publishing to several hosts reports the most severe one
+#[derive(PartialEq, PartialOrd)]
+enum PublishOutcome {
+    Standby,
+    Unchanged,
+    Updated,
+    Failed,
+    AuthFailed,
+}
+
+// Provider instances by HostConfig::key(), with the settings they were built from
+type ProviderCache = HashMap<String, (HostConfig, Arc<dyn Provider>)>;
+
+// Last published state of a record, keyed by HostConfig::record_key()
+#[derive(Default)]
+struct RecordState {
+    ip: Option<String>,
+    last_change: Option<DateTime<Local>>,
+    last_attempt: Option<Instant>,
+}
+
+// Pushes an IP to every configured host unless it matches the one last
+// published there.
+async fn publish_ip(state: &Arc<AppState>, ip: &str) -> PublishOutcome {
+    let Some(config) = state.config.read().await.clone() else {
+        error!("✗ No valid config available");
+        return PublishOutcome::Failed;
     };
+    publish_to(state, &config, &config.hosts, ip).await
+}
 
-    let ip_cache = state.ip_cache.read().await;
-    if ip_cache.as_ref() == Some(&ip) {
-        let last_change = state.last_change_time.read().await;
-        if let Some(time) = *last_change {
+// Publishes to the given hosts and returns the most severe outcome
+async fn publish_to(
+    state: &Arc<AppState>,
+    config: &Config,
+    hosts: &[HostConfig],
+    ip: &str,
+) -> PublishOutcome {
+    if config.is_standby(state) {
+        info!(
+            "Standby: public IP is {} (updates handled by the active instance)",
+            ip
+        );
+        return PublishOutcome::Standby;
+    }
+
+    let Ok(addr) = ip.parse::<IpAddr>() else {
+        error!("✗ Invalid IP address: '{}'", ip);
+        return PublishOutcome::Failed;
+    };
+    let family = IpFamily::of(&addr);
+    let hosts: Vec<&HostConfig> = hosts.iter().filter(|h| h.takes(&addr)).collect();
+    if hosts.is_empty() {
+        info!(
+            "No host takes {} records - ignoring {}",
+            family.record_type(),
+            ip
+        );
+        return PublishOutcome::Unchanged;
+    }
+
+    // A fixed address is set on purpose, so only detected and pushed ones are
+    // checked
+    if !detect::is_public(&addr) && hosts.iter().any(|h| h.ip.is_none()) {
+        match config.private_ips {
+            PrivateIpPolicy::Skip => {
+                error!(
+                    "✗ Refusing to publish {}: not a public address (CGNAT, private or reserved range) - set private_ips to allow it",
+                    ip
+                );
+                for host in &hosts {
+                    let old_ip = record_ip(state, host, family).await;
+                    audit(
+                        state,
+                        config,
+                        host,
+                        old_ip.as_deref(),
+                        ip,
+                        "refused",
+                        "not a public address",
+                    )
+                    .await;
+                }
+                return PublishOutcome::Failed;
+            }
+            PrivateIpPolicy::Warn => warn!(
+                "⚠ Publishing {}, which is not a public address (CGNAT, private or reserved range)",
+                ip
+            ),
+            PrivateIpPolicy::Allow => {}
+        }
+    }
+
+    if addr.is_ipv6() && !config.ipv6_probe.is_empty() && hosts.iter().any(|h| h.ip.is_none()) {
+        if let Err(e) = probe_ipv6(config, addr).await {
+            error!(
+                "✗ Refusing to publish {}: it cannot reach the internet ({})",
+                ip, e
+            );
+            for host in &hosts {
+                let old_ip = record_ip(state, host, family).await;
+                audit(
+                    state,
+                    config,
+                    host,
+                    old_ip.as_deref(),
+                    ip,
+                    "refused",
+                    "IPv6 probe failed",
+                )
+                .await;
+            }
+            return PublishOutcome::Failed;
+        }
+    }
+
+    let mut outcome = PublishOutcome::Unchanged;
+    for host in hosts {
+        let host_addr = host.address_for(&addr);
+        let result = if !host.accepts_ip(&host_addr) {
+            error!(
+                "✗ Refusing to publish {} to {}: outside its allowed_ips or within its denied_ips",
+                host_addr,
+                host.name()
+            );
+            let old_ip = record_ip(state, host, family).await;
+            audit(
+                state,
+                config,
+                host,
+                old_ip.as_deref(),
+                &host_addr.to_string(),
+                "refused",
+                "outside the host's allowed_ips or within its denied_ips",
+            )
+            .await;
+            PublishOutcome::Failed
+        } else {
+            publish_host(state, config, host, &host_addr, false).await
+        };
+        if result > outcome {
+            outcome = result;
+        }
+    }
+    outcome
+}
+
+async fn record_ip(state: &AppState, host: &HostConfig, family: IpFamily) -> Option<String> {
+    state
+        .records
+        .read()
+        .await
+        .get(&host.record_key(family))
+        .and_then(|s| s.ip.clone())
+}
+
+// Pushes an IP to one host. With `drift` set the IP is pushed even if it
+// matches the last published one, because the record was found to point
+// somewhere else.
+async fn publish_host(
+    state: &Arc<AppState>,
+    config: &Config,
+    host: &HostConfig,
+    ip: &IpAddr,
+    drift: bool,
+) -> PublishOutcome {
+    let addr = *ip;
+    let record_key = host.record_key(IpFamily::of(ip));
+    let ip = ip.to_string();
+    let key = host.key();
+    let name = host.name();
+
+    if is_blocked(state, host).await {
+        warn!(
+            "⚠ Skipping {}: blocked by the provider - resolve it with the provider, then change the host's config to retry",
+            name
+        );
+        return PublishOutcome::Failed;
+    }
+
+    let provider = match provider_for(state, host).await {
+        Ok(provider) => provider,
+        Err(e) => {
+            error!("✗ Cannot update {}: {}", name, e);
+            return PublishOutcome::Failed;
+        }
+    };
+
+    // The request would only be rejected
+    if addr.is_ipv6() && !provider.supports_ipv6() {
+        warn!(
+            "⚠ Skipping {} for {}: the {} provider has no AAAA records",
+            ip, name, host.provider
+        );
+        return PublishOutcome::Unchanged;
+    }
+
+    let (old_ip, last_change) = {
+        let mut records = state.records.write().await;
+        let record = records.entry(record_key.clone()).or_default();
+        record.last_attempt = Some(Instant::now());
+        (record.ip.clone(), record.last_change)
+    };
+
+    if old_ip.is_none()
+        && !drift
+        && config.record_check != RecordCheck::Off
+        && record_matches(&config.record_check, host, &addr).await
+    {
+        info!("✓ {} already points to {} - skipping the update", name, ip);
+        if let Some(record) = state.records.write().await.get_mut(&record_key) {
+            record.ip = Some(ip);
+        }
+        return PublishOutcome::Unchanged;
+    }
+
+    let unchanged = old_ip.as_ref() == Some(&ip);
+    let refresh = unchanged && !drift && refresh_due(provider.as_ref(), last_change);
+    if unchanged && !drift && !refresh {
+        if let Some(time) = last_change {
             info!(
-                "✓ IP unchanged: {} (last changed {})",
+                "✓ IP unchanged for {}: {} (last changed {})",
+                name,
                 ip,
                 time.format("%Y-%m-%d %H:%M:%S")
             );
         } else {
-            info!("✓ IP unchanged: {} (change time unknown)", ip);
+            info!("✓ IP unchanged for {}: {} (change time unknown)", name, ip);
         }
-        return;
+        return PublishOutcome::Unchanged;
     }
-    drop(ip_cache);
 
-    info!("⚠ IP changed to: {}", ip);
+    if drift {
+        info!("Re-publishing {} to {} to correct DNS drift", ip, name);
+    } else if refresh {
+        info!(
+            "Refreshing unchanged IP {} for {} before the record expires",
+            ip, name
+        );
+    } else {
+        info!("⚠ IP changed to: {} (updating {})", ip, name);
+    }
 
-    let config = {
-        let config_guard = state.config.read().await;
-        match config_guard.as_ref() {
-            Some(c) => c.clone(),
-            None => {
-                error!("✗ No valid config available");
-                return;
+    let client = state.update_client.read().await.clone();
+    let mut outcome = provider.update(&client, addr).await;
+    if let UpdateOutcome::AuthFailed(_) = outcome {
+        let invalidated = provider.invalidate().await;
+
+        // Credentials may have been rotated on disk without a change event
+        let reloaded = matches!(
+            load_config(&state.config_path, state.clone(), false).await,
+            ConfigLoadResult::Success
+        );
+        if reloaded || invalidated {
+            let host = state
+                .config
+                .read()
+                .await
+                .as_ref()
+                .and_then(|c| c.hosts.iter().find(|h| h.key() == key).cloned());
+            if let Some(host) = host {
+                if let Ok(provider) = provider_for(state, &host).await {
+                    info!("Credentials refreshed, retrying update");
+                    outcome = provider.update(&client, addr).await;
+                }
             }
         }
-    };
+    }
 
-    if let Err(e) = update_ddns(&state.client, &config, &ip).await {
-        error!("✗ DDNS update failed: {}", e);
-        if e.to_string().contains("401") || e.to_string().contains("403") {
+    match &outcome {
+        UpdateOutcome::Good | UpdateOutcome::NoChange => {}
+        UpdateOutcome::AuthFailed(e) => {
+            error!("✗ DDNS update failed for {}: {}", name, e);
+            audit(
+                state,
+                config,
+                host,
+                old_ip.as_deref(),
+                &ip,
+                "auth_failed",
+                e,
+            )
+            .await;
             error!("⚠ Authentication failed - check username/password in config");
-        } else if e.to_string().contains("dns")
-            || e.to_string().contains("connect")
-            || e.to_string().contains("timeout")
-        {
-        } else if e.to_string().contains("404") {
-            error!("⚠ DDNS provider not found - check ddns URL in config");
+            return PublishOutcome::AuthFailed;
+        }
+        UpdateOutcome::Failed(e) => {
+            error!("✗ DDNS update failed for {}: {}", name, e);
+            audit(state, config, host, old_ip.as_deref(), &ip, "failed", e).await;
+            if e.contains("404") {
+                error!("⚠ DDNS provider not found - check ddns URL in config");
+            }
+            return PublishOutcome::Failed;
         }
+        UpdateOutcome::Blocked(e) => {
+            error!("✗ DDNS update failed for {}: {}", name, e);
+            audit(state, config, host, old_ip.as_deref(), &ip, "blocked", e).await;
+            error!(
+                "⚠ {} is locked - no more updates are sent until its config changes",
+                name
+            );
+            state.blocked.write().await.insert(key, host.clone());
+            return PublishOutcome::Failed;
+        }
+    }
+
+    {
+        let mut records = state.records.write().await;
+        let record = records.entry(record_key).or_default();
+        record.ip = Some(ip.clone());
+        record.last_change = Some(Local::now());
+    }
+    info!("✓ DDNS updated successfully for {} with IP: {}", name, ip);
+    let audit_outcome = if drift {
+        "drift_corrected"
+    } else if refresh {
+        "refreshed"
+    } else {
+        "updated"
+    };
+    let response = if outcome == UpdateOutcome::NoChange {
+        "nochg"
+    } else {
+        "ok"
+    };
+    audit(
+        state,
+        config,
+        host,
+        old_ip.as_deref(),
+        &ip,
+        audit_outcome,
+        response,
+    )
+    .await;
+
+    if let Some(verify) = &config.verify_update {
+        if !host.hostname.is_empty() {
+            tokio::spawn(verify_update(
+                state.clone(),
+                config.clone(),
+                verify.clone(),
+                host.clone(),
+                addr,
+            ));
+        }
+    }
+    PublishOutcome::Updated
+}
+
+// Catches providers that confirm an update but never apply it. Gives up
+// quietly once a newer address was published to the record.
+async fn verify_update(
+    state: Arc<AppState>,
+    config: Config,
+    verify: VerifyConfig,
+    host: HostConfig,
+    ip: IpAddr,
+) {
+    let family = IpFamily::of(&ip);
+    let started = Instant::now();
+    loop {
+        match dns::lookup(&verify.resolver, &host.hostname, &host.zone, family).await {
+            Ok(current) if current.contains(&ip) => {
+                info!(
+                    "✓ Verified: {} resolves to {} after {}s",
+                    host.hostname,
+                    ip,
+                    started.elapsed().as_secs()
+                );
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => warn!("⚠ Cannot verify the update of {}: {}", host.hostname, e),
+        }
+
+        if started.elapsed() >= Duration::from_secs(verify.timeout) {
+            break;
+        }
+        sleep(Duration::from_secs(10)).await;
+        if record_ip(&state, &host, family).await != Some(ip.to_string()) {
+            return;
+        }
+    }
+
+    error!(
+        "✗ {} still does not resolve to {} {}s after the provider confirmed the update",
+        host.hostname, ip, verify.timeout
+    );
+    audit(
+        &state,
+        &config,
+        &host,
+        None,
+        &ip.to_string(),
+        "unverified",
+        "record did not change",
+    )
+    .await;
+}
+
+// Whether the host's live record already holds the address. A failed lookup
+// counts as a mismatch, so the update goes ahead.
+async fn record_matches(check: &RecordCheck, host: &HostConfig, ip: &IpAddr) -> bool {
+    if host.hostname.is_empty() {
+        return false;
+    }
+    match dns::lookup(check, &host.hostname, &host.zone, IpFamily::of(ip)).await {
+        Ok(current) => current.contains(ip),
+        Err(e) => {
+            warn!(
+                "⚠ Cannot look up the current record of {}: {}",
+                host.hostname, e
+            );
+            false
+        }
+    }
+}
+
+// Whether the provider expires records that were not updated for a while and
+// the last successful update is old enough to push the same IP again
+fn refresh_due(provider: &dyn Provider, last_change: Option<DateTime<Local>>) -> bool {
+    let Some(refresh_interval) = provider.refresh_interval() else {
+        return false;
+    };
+
+    match last_change {
+        Some(time) => (Local::now() - time)
+            .to_std()
+            .is_ok_and(|elapsed| elapsed >= refresh_interval),
+        None => true,
+    }
+}
+
+async fn audit(
+    state: &AppState,
+    config: &Config,
+    host: &HostConfig,
+    old_ip: Option<&str>,
+    new_ip: &str,
+    outcome: &str,
+    response: &str,
+) {
+    let Some(audit_config) = &config.audit_log else {
         return;
+    };
+
+    let entry = AuditEntry {
+        host: host.name(),
+        provider: &host.provider,
+        old_ip,
+        new_ip,
+        outcome,
+        response,
+    };
+    if let Err(e) = state.audit.record(audit_config, entry).await {
+        warn!("Failed to write audit log {}: {}", audit_config.path, e);
+    }
+}
+
+// A block is lifted once the host's settings change, e.g. after the account
+// was sorted out with the provider
+async fn is_blocked(state: &AppState, host: &HostConfig) -> bool {
+    let mut blocked = state.blocked.write().await;
+    match blocked.get(&host.key()) {
+        Some(settings) if settings == host => true,
+        Some(_) => {
+            blocked.remove(&host.key());
+            false
+        }
+        None => false,
+    }
+}
+
+// Returns the provider for a host, building a new one (and dropping cached
+// state such as access tokens) whenever the host's settings change.
+async fn provider_for(state: &AppState, host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    let mut providers = state.providers.write().await;
+    if let Some((current, provider)) = providers.get(&host.key()) {
+        if current == host {
+            return Ok(provider.clone());
+        }
     }
 
-    *state.ip_cache.write().await = Some(ip.clone());
-    *state.last_change_time.write().await = Some(Local::now());
-    info!("✓ DDNS updated successfully with IP: {}", ip);
+    let provider = providers::build(host)?;
+    providers.insert(host.key(), (host.clone(), provider.clone()));
+    Ok(provider)
+}
+
+// Fetches ipv6_probe from the address itself, which therefore has to be
+// assigned to this machine
+async fn probe_ipv6(config: &Config, addr: IpAddr) -> Result<(), String> {
+    transport::build_bound_client(&config.http.detection, addr)
+        .get(&config.ipv6_probe)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map(|_| ())
+        .map_err(|e| {
+            if e.is_timeout() {
+                "probe timeout".to_string()
+            } else if e.is_connect() {
+                "cannot connect to the probe URL".to_string()
+            } else {
+                format!("probe failed: {}", e)
+            }
+        })
 }
 
 async fn check_internet_connectivity(
     client: &reqwest::Client,
+    family: IpFamily,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Try to connect to a reliable endpoint (Cloudflare DNS)
     client
-        .get("https://1.1.1.1")
+        .get(family.connectivity_url())
         .timeout(Duration::from_secs(5))
         .send()
         .await
@@ -331,58 +2167,99 @@ async fn check_internet_connectivity(
     Ok(())
 }
 
-async fn get_public_ip(client: &reqwest::Client) -> Result<String, Box<dyn std::error::Error>> {
-    let resp = client
-        .get("https://api.ipify.org")
-        .send()
-        .await
-        .map_err(|e| {
-            if e.is_timeout() {
-                "timeout - check internet connection".to_string()
-            } else if e.is_connect() {
-                "connection failed - check internet connection".to_string()
-            } else {
-                format!("network error: {}", e)
-            }
-        })?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if !resp.status().is_success() {
-        return Err(format!("API returned status: {}", resp.status()).into());
+    fn host_with_suffix(suffix: &str, prefix_length: Option<u8>) -> HostConfig {
+        HostConfig {
+            ipv6_suffix: Some(suffix.parse().unwrap()),
+            ipv6_prefix_length: prefix_length,
+            ..HostConfig::default()
+        }
     }
 
-    let ip = resp.text().await?;
-    Ok(ip.trim().to_string())
-}
-
-async fn update_ddns(
-    client: &reqwest::Client,
-    config: &Config,
-    ip: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let url = format!(
-        "https://{}:{}@{}?myip={}",
-        config.user, config.pass, config.ddns, ip
-    );
-
-    let resp = client.get(&url).send().await.map_err(|e| {
-        if e.is_timeout() {
-            "timeout - check internet connection".to_string()
-        } else if e.is_connect() {
-            "connection failed - check ddns provider".to_string()
-        } else {
-            format!("request error: {}", e)
+    #[test]
+    fn address_for_combines_prefix_and_suffix() {
+        let detected: IpAddr = "2001:db8:abcd:ef01::1".parse().unwrap();
+        let cases = [
+            // (suffix, prefix length, expected)
+            ("::10", None, "2001:db8:abcd:ef01::10"),
+            ("::10", Some(64), "2001:db8:abcd:ef01::10"),
+            ("::12:0:0:0:10", Some(56), "2001:db8:abcd:ef12::10"),
+            // No prefix: the suffix is the whole address
+            ("::10", Some(0), "::10"),
+            // The whole detected address is the prefix
+            ("::10", Some(128), "2001:db8:abcd:ef01::1"),
+            ("::10", Some(200), "2001:db8:abcd:ef01::1"),
+            // Suffix bits inside the prefix are replaced by the detected ones
+            ("ffff::10", Some(64), "2001:db8:abcd:ef01::10"),
+            ("::ffff:0:0:0:10", Some(56), "2001:db8:abcd:efff::10"),
+        ];
+        for (suffix, prefix_length, expected) in cases {
+            let host = host_with_suffix(suffix, prefix_length);
+            assert_eq!(
+                host.address_for(&detected),
+                expected.parse::<IpAddr>().unwrap(),
+                "suffix {} with /{:?}",
+                suffix,
+                prefix_length
+            );
         }
-    })?;
+    }
+
+    #[test]
+    fn address_for_leaves_other_addresses_alone() {
+        let host = host_with_suffix("::10", Some(64));
+        let ipv4: IpAddr = "203.0.113.7".parse().unwrap();
+        assert_eq!(host.address_for(&ipv4), ipv4);
 
-    let status = resp.status();
-    if !status.is_success() {
-        return Err(format!(
-            "status: {} ({})",
-            status.as_u16(),
-            status.canonical_reason().unwrap_or("Unknown")
-        )
-        .into());
+        let ipv6: IpAddr = "2001:db8::1".parse().unwrap();
+        assert_eq!(HostConfig::default().address_for(&ipv6), ipv6);
     }
 
-    Ok(())
+    #[test]
+    fn accepts_ip_applies_ranges_per_family() {
+        let nets =
+            |nets: &[&str]| -> Vec<IpNet> { nets.iter().map(|n| n.parse().unwrap()).collect() };
+        let cases: [(&[&str], &[&str], &str, bool); 9] = [
+            // (allowed_ips, denied_ips, address, accepted)
+            (&[], &[], "203.0.113.7", true),
+            (&[], &[], "2001:db8::1", true),
+            (&["203.0.113.0/24"], &[], "203.0.113.7", true),
+            (&["203.0.113.0/24"], &[], "198.51.100.7", false),
+            // IPv4 ranges do not restrict IPv6 addresses
+            (&["203.0.113.0/24"], &[], "2001:db8::1", true),
+            (
+                &["203.0.113.0/24", "2001:db8::/32"],
+                &[],
+                "2001:db9::1",
+                false,
+            ),
+            (&[], &["10.0.0.0/8"], "10.1.2.3", false),
+            (&[], &["10.0.0.0/8"], "203.0.113.7", true),
+            // Denied wins over allowed
+            (
+                &["203.0.113.0/24"],
+                &["203.0.113.7/32"],
+                "203.0.113.7",
+                false,
+            ),
+        ];
+        for (allowed, denied, ip, accepted) in cases {
+            let host = HostConfig {
+                allowed_ips: nets(allowed),
+                denied_ips: nets(denied),
+                ..HostConfig::default()
+            };
+            assert_eq!(
+                host.accepts_ip(&ip.parse().unwrap()),
+                accepted,
+                "{} with allowed {:?} and denied {:?}",
+                ip,
+                allowed,
+                denied
+            );
+        }
+    }
 }
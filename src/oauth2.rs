@@ -0,0 +1,140 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+// Tokens are refreshed this long before they expire so a request never goes out
+// with a token that lapses in flight.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+const DEFAULT_LIFETIME: u64 = 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OAuth2Config {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub client_auth: ClientAuth,
+}
+
+// How the client credentials are presented to the token endpoint: HTTP Basic
+// (client_secret_basic) or form fields in the request body (client_secret_post).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientAuth {
+    #[default]
+    Basic,
+    Post,
+}
+
+impl OAuth2Config {
+    pub fn is_valid(&self) -> bool {
+        !self.token_url.is_empty() && !self.client_id.is_empty() && !self.client_secret.is_empty()
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+pub struct TokenManager {
+    config: OAuth2Config,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl TokenManager {
+    pub fn new(config: OAuth2Config) -> Self {
+        Self {
+            config,
+            cached: Mutex::new(None),
+        }
+    }
+
+    // Returns the cached access token, fetching a new one if there is none or
+    // it is about to expire.
+    pub async fn token(
+        &self,
+        client: &reqwest::Client,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if token.expires_at > Instant::now() + REFRESH_MARGIN {
+                return Ok(token.access_token.clone());
+            }
+        }
+
+        let token = self.fetch(client).await?;
+        let access_token = token.access_token.clone();
+        *cached = Some(token);
+        Ok(access_token)
+    }
+
+    // Drops the cached token, e.g. after the API rejected it.
+    pub async fn invalidate(&self) {
+        *self.cached.lock().await = None;
+    }
+
+    async fn fetch(
+        &self,
+        client: &reqwest::Client,
+    ) -> Result<CachedToken, Box<dyn std::error::Error>> {
+        let scope = self.config.scopes.join(" ");
+        let mut form = vec![("grant_type", "client_credentials")];
+        if !scope.is_empty() {
+            form.push(("scope", scope.as_str()));
+        }
+
+        let mut request = client.post(&self.config.token_url);
+        match self.config.client_auth {
+            ClientAuth::Basic => {
+                request =
+                    request.basic_auth(&self.config.client_id, Some(&self.config.client_secret));
+            }
+            ClientAuth::Post => {
+                form.push(("client_id", self.config.client_id.as_str()));
+                form.push(("client_secret", self.config.client_secret.as_str()));
+            }
+        }
+
+        let resp = request.form(&form).send().await.map_err(|e| {
+            if e.is_timeout() {
+                "token request timeout - check internet connection".to_string()
+            } else if e.is_connect() {
+                "connection failed - check token_url".to_string()
+            } else {
+                format!("token request error: {}", e)
+            }
+        })?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(format!(
+                "token endpoint status: {} ({})",
+                status.as_u16(),
+                status.canonical_reason().unwrap_or("Unknown")
+            )
+            .into());
+        }
+
+        let token: TokenResponse = resp
+            .json()
+            .await
+            .map_err(|e| format!("invalid token response: {}", e))?;
+        let lifetime = token.expires_in.unwrap_or(DEFAULT_LIFETIME);
+        info!("✓ OAuth2 token obtained (valid for {}s)", lifetime);
+
+        Ok(CachedToken {
+            access_token: token.access_token,
+            expires_at: Instant::now() + Duration::from_secs(lifetime),
+        })
+    }
+}
@@ -0,0 +1,341 @@
+use super::{
+    api_error, hex, hmac_sha256, percent_encode, request_error, split_hostname, Provider,
+    UpdateOutcome,
+};
+use crate::HostConfig;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const API: &str = "alidns.aliyuncs.com";
+const VERSION: &str = "2015-01-09";
+
+// Requests are signed with the AccessKey (ACS3-HMAC-SHA256). The record ID is
+// looked up on the first update and cached, so later updates are a single
+// UpdateDomainRecord call. A failed update drops the cached ID, so a record
+// deleted in the meantime is looked up (or added) again.
+struct Aliyun {
+    api: String,
+    access_key_id: String,
+    access_key_secret: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+    record_ids: Mutex<HashMap<&'static str, String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SubDomainRecords {
+    domain_records: DomainRecords,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DomainRecords {
+    record: Vec<Record>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Record {
+    record_id: String,
+    #[serde(rename = "RR")]
+    rr: String,
+    #[serde(rename = "Type")]
+    record_type: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RecordId {
+    record_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ApiError {
+    code: String,
+    message: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() || host.secret.is_empty() {
+        return Err(
+            "token (AccessKey ID) and secret (AccessKey secret) are required by the aliyun provider"
+                .to_string(),
+        );
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Aliyun {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        access_key_id: host.token.clone(),
+        access_key_secret: host.secret.clone(),
+        zone,
+        // The zone apex is `@` in AliDNS
+        name: if name.is_empty() {
+            "@".to_string()
+        } else {
+            name
+        },
+        ttl: host.ttl,
+        record_ids: Mutex::new(HashMap::new()),
+    }))
+}
+
+impl Aliyun {
+    async fn call<T: DeserializeOwned>(
+        &self,
+        client: &reqwest::Client,
+        action: &str,
+        params: &[(&str, String)],
+    ) -> Result<T, UpdateOutcome> {
+        let mut params = params.to_vec();
+        params.sort();
+        let query = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", percent_encode(key), percent_encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let mut request = client.post(format!("https://{}/?{}", self.api, query));
+        for (name, value) in self.sign(action, &query, Utc::now()) {
+            request = request.header(name, value);
+        }
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let Ok(error) = resp.json::<ApiError>().await else {
+                return Err(api_error(status, None));
+            };
+            let message = format!("{} ({})", error.code, error.message);
+            return Err(match error.code.as_str() {
+                // Setting the value the record already has
+                "DomainRecordDuplicate" => UpdateOutcome::NoChange,
+                code if code.starts_with("InvalidAccessKeyId")
+                    || code.starts_with("SignatureDoesNotMatch")
+                    || code.starts_with("Forbidden") =>
+                {
+                    UpdateOutcome::AuthFailed(message)
+                }
+                _ => UpdateOutcome::Failed(message),
+            });
+        }
+
+        resp.json()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(format!("unexpected response: {}", e)))
+    }
+
+    // ACS3-HMAC-SHA256 signature; returns the headers to add to the request
+    fn sign(&self, action: &str, query: &str, now: DateTime<Utc>) -> Vec<(&'static str, String)> {
+        let headers = vec![
+            (
+                "host",
+                self.api.split('/').next().unwrap_or_default().to_string(),
+            ),
+            ("x-acs-action", action.to_string()),
+            ("x-acs-content-sha256", hex(&Sha256::digest(b""))),
+            ("x-acs-date", now.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+            (
+                "x-acs-signature-nonce",
+                now.timestamp_nanos_opt().unwrap_or_default().to_string(),
+            ),
+            ("x-acs-version", VERSION.to_string()),
+        ];
+        authorize(&self.access_key_id, &self.access_key_secret, query, headers)
+    }
+
+    // Returns the cached record ID, or looks the record up. `Ok(None)` means
+    // there is no record of this type yet.
+    async fn find_record(
+        &self,
+        client: &reqwest::Client,
+        record_type: &'static str,
+        ip: IpAddr,
+    ) -> Result<Option<String>, UpdateOutcome> {
+        if let Some(id) = self.record_ids.lock().await.get(record_type) {
+            return Ok(Some(id.clone()));
+        }
+
+        let subdomain = if self.name == "@" {
+            self.zone.clone()
+        } else {
+            format!("{}.{}", self.name, self.zone)
+        };
+        let records: SubDomainRecords = self
+            .call(
+                client,
+                "DescribeSubDomainRecords",
+                &[
+                    ("SubDomain", subdomain),
+                    ("DomainName", self.zone.clone()),
+                    ("Type", record_type.to_string()),
+                ],
+            )
+            .await?;
+        let Some(record) = records
+            .domain_records
+            .record
+            .into_iter()
+            .find(|r| r.rr == self.name && r.record_type == record_type)
+        else {
+            return Ok(None);
+        };
+
+        self.record_ids
+            .lock()
+            .await
+            .insert(record_type, record.record_id.clone());
+        if record.value == ip.to_string() {
+            return Err(UpdateOutcome::NoChange);
+        }
+        Ok(Some(record.record_id))
+    }
+}
+
+#[async_trait]
+impl Provider for Aliyun {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+
+        let record_id = match self.find_record(client, record_type, ip).await {
+            Ok(id) => id,
+            Err(outcome) => return outcome,
+        };
+
+        let mut params = vec![
+            ("RR", self.name.clone()),
+            ("Type", record_type.to_string()),
+            ("Value", ip.to_string()),
+        ];
+        if let Some(ttl) = self.ttl {
+            params.push(("TTL", ttl.to_string()));
+        }
+        let result = match record_id {
+            Some(id) => {
+                params.push(("RecordId", id));
+                self.call::<RecordId>(client, "UpdateDomainRecord", &params)
+                    .await
+            }
+            None => {
+                params.push(("DomainName", self.zone.clone()));
+                self.call::<RecordId>(client, "AddDomainRecord", &params)
+                    .await
+            }
+        };
+
+        match result {
+            Ok(record) => {
+                self.record_ids
+                    .lock()
+                    .await
+                    .insert(record_type, record.record_id);
+                UpdateOutcome::Good
+            }
+            Err(outcome) => {
+                if matches!(outcome, UpdateOutcome::Failed(_)) {
+                    self.record_ids.lock().await.remove(record_type);
+                }
+                outcome
+            }
+        }
+    }
+}
+
+// Signs a bodiless POST with the given headers, which must be sorted by name
+// and include `host`. The `host` header is replaced by the authorization, as
+// reqwest sets it itself.
+fn authorize(
+    access_key_id: &str,
+    access_key_secret: &str,
+    query: &str,
+    headers: Vec<(&'static str, String)>,
+) -> Vec<(&'static str, String)> {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "POST\n/\n{}\n{}\n{}\n{}",
+        query,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(b""))
+    );
+    let string_to_sign = format!(
+        "ACS3-HMAC-SHA256\n{}",
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex(&hmac_sha256(access_key_secret.as_bytes(), &string_to_sign));
+
+    let mut headers: Vec<_> = headers
+        .into_iter()
+        .filter(|(name, _)| *name != "host")
+        .collect();
+    headers.push((
+        "authorization",
+        format!(
+            "ACS3-HMAC-SHA256 Credential={},SignedHeaders={},Signature={}",
+            access_key_id, signed_headers, signature
+        ),
+    ));
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authorize_matches_the_aliyun_example() {
+        // The RunInstances example of the Alibaba Cloud V3 signature documentation
+        let headers = vec![
+            ("host", "ecs.cn-shanghai.aliyuncs.com".to_string()),
+            ("x-acs-action", "RunInstances".to_string()),
+            ("x-acs-content-sha256", hex(&Sha256::digest(b""))),
+            ("x-acs-date", "2023-10-26T10:22:32Z".to_string()),
+            (
+                "x-acs-signature-nonce",
+                "3156853299f313e23d1673dc12e1703d".to_string(),
+            ),
+            ("x-acs-version", "2014-05-26".to_string()),
+        ];
+        let headers = authorize(
+            "YourAccessKeyId",
+            "YourAccessKeySecret",
+            "ImageId=win2019_1809_x64_dtc_zh-cn_40G_alibase_20230811.vhd&RegionId=cn-shanghai",
+            headers,
+        );
+        assert!(!headers.iter().any(|(name, _)| *name == "host"));
+        assert_eq!(
+            headers.last(),
+            Some(&(
+                "authorization",
+                "ACS3-HMAC-SHA256 Credential=YourAccessKeyId,SignedHeaders=host;x-acs-action;x-acs-content-sha256;x-acs-date;x-acs-signature-nonce;x-acs-version,Signature=06563a9e1b43f5dfe96b81484da74bceab24a1d853912eee15083a6f0f3283c0".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,288 @@
+use super::{
+    api_error, request_error, split_hostname, xml_element, xml_unescape, Provider, UpdateOutcome,
+};
+use crate::HostConfig;
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const API: &str = "kasapi.kasserver.com/soap";
+
+// all-inkl's KAS API is SOAP with the actual parameters as a JSON string. Each
+// update logs in through KasAuth for a short-lived session token, then reads
+// the zone's records and updates or adds the one of `hostname`. KAS enforces a
+// pause between calls (KasFloodDelay), which is waited out before the next one.
+struct AllInkl {
+    api: String,
+    login: String,
+    password: String,
+    zone: String,
+    name: String,
+    // Matches leaf `<key>..</key><value>..</value>` pairs of a response
+    pair: Regex,
+}
+
+// A record as returned by get_dns_settings
+struct Record {
+    id: String,
+    name: String,
+    record_type: String,
+    data: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.user.is_empty() || host.pass.is_empty() {
+        return Err(
+            "user (KAS login) and pass (KAS password) are required by the allinkl provider"
+                .to_string(),
+        );
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(AllInkl {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        login: host.user.clone(),
+        password: host.pass.clone(),
+        zone,
+        name,
+        pair: Regex::new(r"<key[^>]*>([^<]*)</key>\s*<value[^>]*>([^<]*)</value>")
+            .expect("valid regex"),
+    }))
+}
+
+impl AllInkl {
+    // Sends one SOAP call and returns the response body
+    async fn soap(
+        &self,
+        client: &reqwest::Client,
+        service: &str,
+        namespace: &str,
+        params: Value,
+    ) -> Result<String, UpdateOutcome> {
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/" "#,
+                r#"xmlns:ns1="{ns}" xmlns:xsd="http://www.w3.org/2001/XMLSchema" "#,
+                r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#,
+                r#"<SOAP-ENV:Body><ns1:{service}><Params xsi:type="xsd:string">{params}</Params>"#,
+                r#"</ns1:{service}></SOAP-ENV:Body></SOAP-ENV:Envelope>"#
+            ),
+            ns = namespace,
+            service = service,
+            params = xml_escape(&params.to_string()),
+        );
+
+        let resp = client
+            .post(format!("https://{}/{}.php", self.api, service))
+            .header(reqwest::header::CONTENT_TYPE, "text/xml; charset=utf-8")
+            .header("SOAPAction", format!("{}#{}", namespace, service))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+
+        let status = resp.status();
+        let body = resp
+            .text()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(format!("unexpected response: {}", e)))?;
+
+        // Errors are SOAP faults, usually sent with status 500
+        if let Some(fault) = xml_element(&body, "faultstring") {
+            return Err(if fault.contains("password") || fault.contains("login") {
+                UpdateOutcome::AuthFailed(fault)
+            } else if fault == "flood_protection" {
+                UpdateOutcome::Failed("flood protection (rate limited, retrying later)".to_string())
+            } else {
+                UpdateOutcome::Failed(fault)
+            });
+        }
+        if !status.is_success() {
+            return Err(api_error(status, None));
+        }
+        Ok(body)
+    }
+
+    async fn session(&self, client: &reqwest::Client) -> Result<String, UpdateOutcome> {
+        let body = self
+            .soap(
+                client,
+                "KasAuth",
+                "urn:xmethodsKasApiAuthentication",
+                json!({
+                    "kas_login": self.login,
+                    "kas_auth_type": "plain",
+                    "kas_auth_data": self.password,
+                    "session_lifetime": 600,
+                    "session_update_lifetime": "Y",
+                }),
+            )
+            .await?;
+        xml_element(&body, "return")
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| UpdateOutcome::Failed("KasAuth returned no session token".to_string()))
+    }
+
+    // Calls a KasApi action; returns the leaf key/value pairs of the response
+    async fn call(
+        &self,
+        client: &reqwest::Client,
+        session: &str,
+        action: &str,
+        params: Value,
+    ) -> Result<Vec<(String, String)>, UpdateOutcome> {
+        let body = self
+            .soap(
+                client,
+                "KasApi",
+                "urn:xmethodsKasApi",
+                json!({
+                    "kas_login": self.login,
+                    "kas_auth_type": "session",
+                    "kas_auth_data": session,
+                    "kas_action": action,
+                    "KasRequestParams": params,
+                }),
+            )
+            .await?;
+
+        let pairs: Vec<(String, String)> = self
+            .pair
+            .captures_iter(&body)
+            .map(|c| (c[1].to_string(), xml_unescape(&c[2])))
+            .collect();
+        if let Some(delay) = pairs
+            .iter()
+            .find(|(key, _)| key == "KasFloodDelay")
+            .and_then(|(_, value)| value.parse::<f64>().ok())
+        {
+            tokio::time::sleep(Duration::from_secs_f64(delay.clamp(0.0, 10.0))).await;
+        }
+        if !pairs
+            .iter()
+            .any(|(key, value)| key == "ReturnString" && value == "TRUE")
+        {
+            return Err(UpdateOutcome::Failed(format!(
+                "{} was not successful",
+                action
+            )));
+        }
+        Ok(pairs)
+    }
+
+    async fn update_record(
+        &self,
+        client: &reqwest::Client,
+        session: &str,
+        ip: IpAddr,
+    ) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let zone_host = format!("{}.", self.zone);
+
+        let pairs = match self
+            .call(
+                client,
+                session,
+                "get_dns_settings",
+                json!({ "zone_host": zone_host }),
+            )
+            .await
+        {
+            Ok(pairs) => pairs,
+            Err(outcome) => return outcome,
+        };
+        let existing = records(&pairs)
+            .into_iter()
+            .find(|r| r.name == self.name && r.record_type == record_type);
+        if existing.as_ref().is_some_and(|r| r.data == ip.to_string()) {
+            return UpdateOutcome::NoChange;
+        }
+
+        let result = match existing {
+            Some(record) => {
+                self.call(
+                    client,
+                    session,
+                    "update_dns_settings",
+                    json!({ "record_id": record.id, "record_data": ip.to_string() }),
+                )
+                .await
+            }
+            None => {
+                self.call(
+                    client,
+                    session,
+                    "add_dns_settings",
+                    json!({
+                        "zone_host": zone_host,
+                        "record_name": self.name,
+                        "record_type": record_type,
+                        "record_data": ip.to_string(),
+                        "record_aux": 0,
+                    }),
+                )
+                .await
+            }
+        };
+        match result {
+            Ok(_) => UpdateOutcome::Good,
+            Err(outcome) => outcome,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for AllInkl {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let session = match self.session(client).await {
+            Ok(session) => session,
+            Err(outcome) => return outcome,
+        };
+        self.update_record(client, &session, ip).await
+    }
+}
+
+// Groups the flat key/value pairs of get_dns_settings into records; a key seen
+// again starts the next record
+fn records(pairs: &[(String, String)]) -> Vec<Record> {
+    let mut groups: Vec<HashMap<&str, &str>> = Vec::new();
+    for (key, value) in pairs {
+        if !key.starts_with("record_") {
+            continue;
+        }
+        match groups.last_mut() {
+            Some(group) if !group.contains_key(key.as_str()) => {
+                group.insert(key, value);
+            }
+            _ => groups.push(HashMap::from([(key.as_str(), value.as_str())])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter_map(|group| {
+            Some(Record {
+                id: group.get("record_id")?.to_string(),
+                name: group.get("record_name").unwrap_or(&"").to_string(),
+                record_type: group.get("record_type")?.to_string(),
+                data: group.get("record_data").unwrap_or(&"").to_string(),
+            })
+        })
+        .collect()
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
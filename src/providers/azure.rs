@@ -0,0 +1,228 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::oauth2::{ClientAuth, OAuth2Config, TokenManager};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+const API: &str = "management.azure.com";
+const API_VERSION: &str = "2018-05-01";
+const LOGIN: &str = "login.microsoftonline.com";
+const IDENTITY_ENDPOINT: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+const DEFAULT_TTL: u32 = 300;
+
+// Where the DNS zone lives in Azure. The service principal's client ID and
+// secret are the host's `user` and `pass`; with `managed_identity` the VM's
+// identity is used instead, and `tenant_id` is not needed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AzureConfig {
+    #[serde(default)]
+    pub tenant_id: String,
+    pub subscription_id: String,
+    pub resource_group: String,
+    #[serde(default)]
+    pub managed_identity: bool,
+    // Client ID of a user-assigned identity; empty for the system-assigned one
+    #[serde(default)]
+    pub client_id: String,
+}
+
+// The record set is replaced with a PUT through the ARM REST API, which also
+// creates it. Access tokens come from the client credentials flow or the
+// instance metadata service and are cached until shortly before they expire.
+struct Azure {
+    api: String,
+    config: AzureConfig,
+    zone: String,
+    name: String,
+    ttl: u32,
+    auth: Auth,
+}
+
+enum Auth {
+    ClientSecret(TokenManager),
+    ManagedIdentity {
+        client_id: String,
+        cached: Mutex<Option<(String, Instant)>>,
+    },
+}
+
+// IMDS sends the lifetime as a string
+#[derive(Deserialize)]
+struct IdentityToken {
+    access_token: String,
+    expires_in: Value,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    let Some(config) = host.azure.clone() else {
+        return Err(
+            "azure (tenant_id, subscription_id, resource_group) is required by the azure provider"
+                .to_string(),
+        );
+    };
+    if config.subscription_id.is_empty() || config.resource_group.is_empty() {
+        return Err("azure needs subscription_id and resource_group".to_string());
+    }
+    let auth = if config.managed_identity {
+        Auth::ManagedIdentity {
+            client_id: config.client_id.clone(),
+            cached: Mutex::new(None),
+        }
+    } else {
+        if config.tenant_id.is_empty() {
+            return Err("azure needs tenant_id unless managed_identity is set".to_string());
+        }
+        if host.user.is_empty() || host.pass.is_empty() {
+            return Err(
+                "user (client ID) and pass (client secret) are required by the azure provider without managed_identity"
+                    .to_string(),
+            );
+        }
+        Auth::ClientSecret(TokenManager::new(OAuth2Config {
+            token_url: format!("https://{}/{}/oauth2/v2.0/token", LOGIN, config.tenant_id),
+            client_id: host.user.clone(),
+            client_secret: host.pass.clone(),
+            scopes: vec![format!("https://{}/.default", API)],
+            client_auth: ClientAuth::Post,
+        }))
+    };
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Azure {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        config,
+        zone,
+        name: if name.is_empty() {
+            "@".to_string()
+        } else {
+            name
+        },
+        ttl: host.ttl.unwrap_or(DEFAULT_TTL),
+        auth,
+    }))
+}
+
+impl Auth {
+    async fn token(&self, client: &reqwest::Client) -> Result<String, String> {
+        let (client_id, cached) = match self {
+            Auth::ClientSecret(manager) => {
+                return manager.token(client).await.map_err(|e| e.to_string())
+            }
+            Auth::ManagedIdentity { client_id, cached } => (client_id, cached),
+        };
+
+        let mut cached = cached.lock().await;
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if *expires_at > Instant::now() + Duration::from_secs(60) {
+                return Ok(token.clone());
+            }
+        }
+
+        let resource = format!("https://{}/", API);
+        let mut query = vec![("api-version", "2018-02-01"), ("resource", &resource)];
+        if !client_id.is_empty() {
+            query.push(("client_id", client_id));
+        }
+        let token: IdentityToken = client
+            .get(IDENTITY_ENDPOINT)
+            .query(&query)
+            .header("Metadata", "true")
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| {
+                format!(
+                    "no managed identity token from the instance metadata service: {}",
+                    e
+                )
+            })?
+            .json()
+            .await
+            .map_err(|e| format!("unexpected managed identity token response: {}", e))?;
+        let lifetime = match &token.expires_in {
+            Value::String(s) => s.parse().ok(),
+            value => value.as_u64(),
+        }
+        .unwrap_or(3600);
+
+        *cached = Some((
+            token.access_token.clone(),
+            Instant::now() + Duration::from_secs(lifetime),
+        ));
+        Ok(token.access_token)
+    }
+
+    async fn invalidate(&self) {
+        match self {
+            Auth::ClientSecret(manager) => manager.invalidate().await,
+            Auth::ManagedIdentity { cached, .. } => *cached.lock().await = None,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Azure {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let token = match self.auth.token(client).await {
+            Ok(token) => token,
+            Err(e) => return UpdateOutcome::AuthFailed(e),
+        };
+
+        let (record_type, properties) = match ip {
+            IpAddr::V4(_) => (
+                "A",
+                json!({ "TTL": self.ttl, "ARecords": [{ "ipv4Address": ip.to_string() }] }),
+            ),
+            IpAddr::V6(_) => (
+                "AAAA",
+                json!({ "TTL": self.ttl, "AAAARecords": [{ "ipv6Address": ip.to_string() }] }),
+            ),
+        };
+
+        let resp = match client
+            .put(format!(
+                "https://{}/subscriptions/{}/resourceGroups/{}/providers/Microsoft.Network/dnsZones/{}/{}/{}",
+                self.api,
+                self.config.subscription_id,
+                self.config.resource_group,
+                self.zone,
+                record_type,
+                self.name
+            ))
+            .query(&[("api-version", API_VERSION)])
+            .bearer_auth(token)
+            .json(&json!({ "properties": properties }))
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+
+        let status = resp.status();
+        if status.is_success() {
+            return UpdateOutcome::Good;
+        }
+
+        let message = resp
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body["error"]["message"].as_str().map(str::to_string));
+        api_error(status, message)
+    }
+
+    async fn invalidate(&self) -> bool {
+        self.auth.invalidate().await;
+        true
+    }
+}
@@ -0,0 +1,344 @@
+use super::{percent_encode, request_error, status_outcome, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::header::{HeaderName, CONTENT_TYPE};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const PLACEHOLDERS: &[&str] = &[
+    "ip", "ipv4", "ipv6", "hostname", "username", "password", "token",
+];
+
+// A provider described entirely by the config, for services without built-in
+// support. `url`, `body` and the header values are templates; see `expand` for
+// the placeholders.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CustomConfig {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub body_format: BodyFormat,
+    #[serde(default)]
+    pub success: Option<SuccessMatcher>,
+}
+
+// What a successful response looks like. Every check that is set has to pass;
+// without any, a 2xx status is success.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SuccessMatcher {
+    // A status code or an inclusive range, e.g. `200` or `200-299`
+    #[serde(default)]
+    pub status: Option<String>,
+    // Regex that has to match somewhere in the body
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub json: Option<JsonMatch>,
+}
+
+// The value at a JSON pointer (RFC 6901) in the body. Without `equals` it only
+// has to exist and be neither `null` nor `false`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct JsonMatch {
+    pub pointer: String,
+    #[serde(default)]
+    pub equals: Option<Value>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+// How values are escaped in the body template, and the default Content-Type
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyFormat {
+    #[default]
+    Json,
+    Form,
+    Text,
+}
+
+impl BodyFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            BodyFormat::Json => "application/json",
+            BodyFormat::Form => "application/x-www-form-urlencoded",
+            BodyFormat::Text => "text/plain",
+        }
+    }
+
+    fn escape(&self) -> fn(&str) -> String {
+        match self {
+            BodyFormat::Json => json_escape,
+            BodyFormat::Form => percent_encode,
+            BodyFormat::Text => str::to_string,
+        }
+    }
+}
+
+struct Custom {
+    config: CustomConfig,
+    method: Method,
+    status: (u16, u16),
+    body_regex: Option<Regex>,
+    hostname: String,
+    user: String,
+    pass: String,
+    token: String,
+    last_v4: Mutex<Option<IpAddr>>,
+    last_v6: Mutex<Option<IpAddr>>,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    let Some(config) = host.custom.clone() else {
+        return Err("custom (url) is required by the custom provider".to_string());
+    };
+    if config.url.is_empty() {
+        return Err("custom.url is missing".to_string());
+    }
+    check_template("custom.url", &config.url)?;
+    let method = Method::from_bytes(config.method.to_uppercase().as_bytes()).map_err(|_| {
+        format!(
+            "custom.method '{}' is not a valid HTTP method",
+            config.method
+        )
+    })?;
+    for (name, value) in &config.headers {
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("custom.headers has an invalid header name '{}'", name))?;
+        check_template(&format!("custom.headers.{}", name), value)?;
+    }
+    if let Some(body) = &config.body {
+        check_template("custom.body", body)?;
+    }
+
+    let success = config.success.clone().unwrap_or_default();
+    let status = match &success.status {
+        Some(status) => parse_status_range(status).ok_or_else(|| {
+            format!(
+                "custom.success.status '{}' is not a status code or range like 200-299",
+                status
+            )
+        })?,
+        None => (200, 299),
+    };
+    let body_regex = success
+        .body
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| format!("custom.success.body is not a valid regex: {}", e))?;
+    if let Some(json) = &success.json {
+        if !json.pointer.is_empty() && !json.pointer.starts_with('/') {
+            return Err(format!(
+                "custom.success.json.pointer '{}' must start with '/'",
+                json.pointer
+            ));
+        }
+    }
+
+    Ok(Arc::new(Custom {
+        config,
+        method,
+        status,
+        body_regex,
+        hostname: host.hostname.clone(),
+        user: host.user.clone(),
+        pass: host.pass.clone(),
+        token: host.token.clone(),
+        last_v4: Mutex::new(None),
+        last_v6: Mutex::new(None),
+    }))
+}
+
+impl Custom {
+    // Substitutes the placeholders in `template`, escaping each value with
+    // `escape`. `{ip}` is the address being published; `{ipv4}` and `{ipv6}`
+    // are the addresses of that family, i.e. the one being published or the
+    // last one published for the other family (empty if none yet).
+    async fn expand(&self, template: &str, ip: IpAddr, escape: fn(&str) -> String) -> String {
+        let (v4, v6) = if ip.is_ipv4() {
+            (Some(ip), *self.last_v6.lock().await)
+        } else {
+            (*self.last_v4.lock().await, Some(ip))
+        };
+        let value = |name: &str| -> String {
+            match name {
+                "ip" => ip.to_string(),
+                "ipv4" => v4.map(|ip| ip.to_string()).unwrap_or_default(),
+                "ipv6" => v6.map(|ip| ip.to_string()).unwrap_or_default(),
+                "hostname" => self.hostname.clone(),
+                "username" => self.user.clone(),
+                "password" => self.pass.clone(),
+                "token" => self.token.clone(),
+                _ => String::new(),
+            }
+        };
+
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            match rest[start + 1..].find('}') {
+                Some(len) if PLACEHOLDERS.contains(&&rest[start + 1..start + 1 + len]) => {
+                    out.push_str(&escape(&value(&rest[start + 1..start + 1 + len])));
+                    rest = &rest[start + len + 2..];
+                }
+                _ => {
+                    out.push('{');
+                    rest = &rest[start + 1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    // Applies the body checks of custom.success, if any
+    async fn check_body(&self, resp: reqwest::Response) -> Result<(), String> {
+        let json = self.config.success.as_ref().and_then(|s| s.json.as_ref());
+        if self.body_regex.is_none() && json.is_none() {
+            return Ok(());
+        }
+        let body = resp
+            .text()
+            .await
+            .map_err(|e| format!("failed to read response: {}", e))?;
+
+        if let Some(regex) = &self.body_regex {
+            if !regex.is_match(&body) {
+                return Err(format!(
+                    "response does not match custom.success.body: {}",
+                    excerpt(&body)
+                ));
+            }
+        }
+
+        if let Some(json) = json {
+            let value: Value = serde_json::from_str(&body)
+                .map_err(|_| format!("response is not JSON: {}", excerpt(&body)))?;
+            let matched = match (value.pointer(&json.pointer), &json.equals) {
+                (Some(actual), Some(expected)) => actual == expected,
+                (Some(actual), None) => !matches!(actual, Value::Null | Value::Bool(false)),
+                (None, _) => false,
+            };
+            if !matched {
+                return Err(format!(
+                    "response does not match custom.success.json at '{}': {}",
+                    json.pointer,
+                    excerpt(&body)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Provider for Custom {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let url = self.expand(&self.config.url, ip, percent_encode).await;
+
+        let mut request = client.request(self.method.clone(), url);
+        for (name, value) in &self.config.headers {
+            // Header values are sent as they are; a value with a line break is
+            // rejected by the HTTP client
+            request = request.header(name, self.expand(value, ip, str::to_string).await);
+        }
+        if let Some(body) = &self.config.body {
+            let format = self.config.body_format;
+            if !self
+                .config
+                .headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case("content-type"))
+            {
+                request = request.header(CONTENT_TYPE, format.content_type());
+            }
+            request = request.body(self.expand(body, ip, format.escape()).await);
+        }
+
+        let resp = match request.send().await {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+        let status = resp.status();
+        if !(self.status.0..=self.status.1).contains(&status.as_u16()) {
+            return status_outcome(status).unwrap_or_else(|| {
+                UpdateOutcome::Failed(format!(
+                    "status: {} (outside custom.success.status)",
+                    status.as_u16()
+                ))
+            });
+        }
+        if let Err(e) = self.check_body(resp).await {
+            return UpdateOutcome::Failed(e);
+        }
+
+        let last = if ip.is_ipv4() {
+            &self.last_v4
+        } else {
+            &self.last_v6
+        };
+        *last.lock().await = Some(ip);
+        UpdateOutcome::Good
+    }
+}
+
+// Rejects `{name}` placeholders that are not known, which are most likely typos
+fn check_template(field: &str, template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start + 1..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + len];
+        if !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !PLACEHOLDERS.contains(&name)
+        {
+            return Err(format!(
+                "{} has unknown placeholder '{{{}}}' (known: {})",
+                field,
+                name,
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+        rest = &rest[start + 1..];
+    }
+    Ok(())
+}
+
+fn parse_status_range(status: &str) -> Option<(u16, u16)> {
+    let (low, high) = status.split_once('-').unwrap_or((status, status));
+    let low: u16 = low.trim().parse().ok()?;
+    let high: u16 = high.trim().parse().ok()?;
+    (100 <= low && low <= high && high <= 599).then_some((low, high))
+}
+
+// Keeps failure messages readable when a service answers with a whole page
+fn excerpt(body: &str) -> String {
+    let body = body.trim();
+    match body.char_indices().nth(200) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body.to_string(),
+    }
+}
+
+// Escapes a value for use inside a JSON string
+fn json_escape(value: &str) -> String {
+    let quoted = Value::from(value).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
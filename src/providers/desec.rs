@@ -0,0 +1,88 @@
+use super::dyndns2::parse_response;
+use super::{request_error, status_outcome, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const ENDPOINT: &str = "update.dedyn.io";
+
+// deSEC sets the A and AAAA records in one request and removes a record whose
+// parameter is left out, so every update carries both addresses: the one being
+// published and the last one sent for the other family, or `preserve` if none
+// was sent yet.
+struct Desec {
+    endpoint: String,
+    hostname: String,
+    token: String,
+    last_v4: Mutex<Option<IpAddr>>,
+    last_v6: Mutex<Option<IpAddr>>,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err("token is required by the desec provider".to_string());
+    }
+    if host.hostname.is_empty() {
+        return Err("hostname is required by the desec provider".to_string());
+    }
+
+    Ok(Arc::new(Desec {
+        endpoint: if host.ddns.is_empty() {
+            ENDPOINT.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        hostname: host.hostname.clone(),
+        token: host.token.clone(),
+        last_v4: Mutex::new(None),
+        last_v6: Mutex::new(None),
+    }))
+}
+
+#[async_trait]
+impl Provider for Desec {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let (last, other) = if ip.is_ipv4() {
+            (&self.last_v4, *self.last_v6.lock().await)
+        } else {
+            (&self.last_v6, *self.last_v4.lock().await)
+        };
+        let other = other.map_or_else(|| "preserve".to_string(), |other| other.to_string());
+        let (myipv4, myipv6) = if ip.is_ipv4() {
+            (ip.to_string(), other)
+        } else {
+            (other, ip.to_string())
+        };
+
+        let request = client
+            .get(format!("https://{}", self.endpoint))
+            .query(&[
+                ("hostname", self.hostname.as_str()),
+                ("myipv4", &myipv4),
+                ("myipv6", &myipv6),
+            ])
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Token {}", self.token),
+            );
+
+        let resp = match request.send().await {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return outcome;
+        }
+
+        let outcome = match resp.text().await {
+            Ok(body) => parse_response(&body, true),
+            Err(e) => UpdateOutcome::Failed(format!("failed to read response: {}", e)),
+        };
+        if matches!(outcome, UpdateOutcome::Good | UpdateOutcome::NoChange) {
+            *last.lock().await = Some(ip);
+        }
+        outcome
+    }
+}
@@ -0,0 +1,404 @@
+use super::{
+    hex, hmac_sha256, request_error, split_hostname, status_outcome, Provider, UpdateOutcome,
+};
+use crate::HostConfig;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const LEGACY_API: &str = "dnsapi.cn";
+const TENCENT_API: &str = "dnspod.tencentcloudapi.com";
+const TENCENT_VERSION: &str = "2021-03-23";
+// The "default" resolution line, which DNSPod only accepts in Chinese
+const DEFAULT_LINE: &str = "默认";
+
+// DNSPod has two APIs: the legacy one authenticated with an API token
+// (`<id>,<token>`), and the TencentCloud API v3 with a SecretId and SecretKey
+// and TC3-HMAC-SHA256 request signing. Either way the record is looked up,
+// then modified, or created if there is none.
+struct DnsPod {
+    api: Api,
+    endpoint: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+}
+
+enum Api {
+    Legacy {
+        login_token: String,
+    },
+    Tencent {
+        secret_id: String,
+        secret_key: String,
+    },
+}
+
+// A record as both APIs describe it, reduced to what an update needs
+struct Record {
+    id: String,
+    value: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err(
+            "token (<id>,<token> API token, or a SecretId with secret) is required by the dnspod provider"
+                .to_string(),
+        );
+    }
+    let (api, default_endpoint) = if host.secret.is_empty() {
+        if !host.token.contains(',') {
+            return Err("token must be the API token as <id>,<token>".to_string());
+        }
+        (
+            Api::Legacy {
+                login_token: host.token.clone(),
+            },
+            LEGACY_API,
+        )
+    } else {
+        (
+            Api::Tencent {
+                secret_id: host.token.clone(),
+                secret_key: host.secret.clone(),
+            },
+            TENCENT_API,
+        )
+    };
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(DnsPod {
+        api,
+        endpoint: if host.ddns.is_empty() {
+            default_endpoint.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        zone,
+        name: if name.is_empty() {
+            "@".to_string()
+        } else {
+            name
+        },
+        ttl: host.ttl,
+    }))
+}
+
+impl DnsPod {
+    async fn find_record(
+        &self,
+        client: &reqwest::Client,
+        record_type: &str,
+    ) -> Result<Option<Record>, UpdateOutcome> {
+        match &self.api {
+            Api::Legacy { .. } => {
+                let body = self
+                    .legacy(
+                        client,
+                        "Record.List",
+                        &[("sub_domain", &self.name), ("record_type", record_type)],
+                    )
+                    .await;
+                let body = match body {
+                    Ok(body) => body,
+                    // No records
+                    Err(UpdateOutcome::Failed(e)) if e.starts_with("10 ") => return Ok(None),
+                    Err(outcome) => return Err(outcome),
+                };
+                let record = body["records"].as_array().and_then(|records| {
+                    records.iter().find(|r| {
+                        r["name"].as_str() == Some(self.name.as_str())
+                            && r["type"].as_str() == Some(record_type)
+                    })
+                });
+                Ok(record.map(|r| Record {
+                    id: value_string(&r["id"]),
+                    value: value_string(&r["value"]),
+                }))
+            }
+            Api::Tencent { .. } => {
+                let body = self
+                    .tencent(
+                        client,
+                        "DescribeRecordList",
+                        json!({
+                            "Domain": self.zone,
+                            "Subdomain": self.name,
+                            "RecordType": record_type,
+                        }),
+                    )
+                    .await;
+                let body = match body {
+                    Ok(body) => body,
+                    Err(UpdateOutcome::Failed(e))
+                        if e.starts_with("ResourceNotFound.NoDataOfRecord") =>
+                    {
+                        return Ok(None)
+                    }
+                    Err(outcome) => return Err(outcome),
+                };
+                let record = body["RecordList"].as_array().and_then(|records| {
+                    records.iter().find(|r| {
+                        r["Name"].as_str() == Some(self.name.as_str())
+                            && r["Type"].as_str() == Some(record_type)
+                    })
+                });
+                Ok(record.map(|r| Record {
+                    id: value_string(&r["RecordId"]),
+                    value: value_string(&r["Value"]),
+                }))
+            }
+        }
+    }
+
+    async fn write_record(
+        &self,
+        client: &reqwest::Client,
+        record_type: &str,
+        ip: IpAddr,
+        record_id: Option<&str>,
+    ) -> Result<Value, UpdateOutcome> {
+        let value = ip.to_string();
+        match &self.api {
+            Api::Legacy { .. } => {
+                let ttl = self.ttl.map(|ttl| ttl.to_string());
+                let mut params = vec![
+                    ("sub_domain", self.name.as_str()),
+                    ("record_type", record_type),
+                    ("record_line", DEFAULT_LINE),
+                    ("value", value.as_str()),
+                ];
+                if let Some(ttl) = &ttl {
+                    params.push(("ttl", ttl));
+                }
+                match record_id {
+                    Some(id) => {
+                        params.push(("record_id", id));
+                        self.legacy(client, "Record.Modify", &params).await
+                    }
+                    None => self.legacy(client, "Record.Create", &params).await,
+                }
+            }
+            Api::Tencent { .. } => {
+                let mut params = json!({
+                    "Domain": self.zone,
+                    "SubDomain": self.name,
+                    "RecordType": record_type,
+                    "RecordLine": DEFAULT_LINE,
+                    "Value": value,
+                });
+                if let Some(ttl) = self.ttl {
+                    params["TTL"] = json!(ttl);
+                }
+                match record_id {
+                    Some(id) => {
+                        params["RecordId"] = json!(id.parse::<u64>().unwrap_or_default());
+                        self.tencent(client, "ModifyRecord", params).await
+                    }
+                    None => self.tencent(client, "CreateRecord", params).await,
+                }
+            }
+        }
+    }
+
+    // Legacy API: form POST to /<action>, result in `status.code` ("1" is success)
+    async fn legacy(
+        &self,
+        client: &reqwest::Client,
+        action: &str,
+        params: &[(&str, &str)],
+    ) -> Result<Value, UpdateOutcome> {
+        let Api::Legacy { login_token } = &self.api else {
+            unreachable!("legacy call with TencentCloud credentials");
+        };
+        let mut form = vec![
+            ("login_token", login_token.as_str()),
+            ("format", "json"),
+            ("domain", self.zone.as_str()),
+        ];
+        form.extend_from_slice(params);
+
+        let resp = client
+            .post(format!("https://{}/{}", self.endpoint, action))
+            // DNSPod blocks clients without a descriptive user agent
+            .header(
+                reqwest::header::USER_AGENT,
+                concat!("ddns-updater/", env!("CARGO_PKG_VERSION")),
+            )
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return Err(outcome);
+        }
+        let body: Value = resp
+            .json()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(format!("unexpected response: {}", e)))?;
+
+        let code = value_string(&body["status"]["code"]);
+        if code == "1" {
+            return Ok(body);
+        }
+        let message = format!(
+            "{} {}",
+            code,
+            body["status"]["message"].as_str().unwrap_or_default()
+        );
+        // -1: login failed, -7/-8: account or token locked
+        if matches!(code.as_str(), "-1" | "-7" | "-8") {
+            Err(UpdateOutcome::AuthFailed(message))
+        } else {
+            Err(UpdateOutcome::Failed(message))
+        }
+    }
+
+    // TencentCloud API v3: JSON POST signed with TC3-HMAC-SHA256, result in
+    // `Response`, errors in `Response.Error`
+    async fn tencent(
+        &self,
+        client: &reqwest::Client,
+        action: &str,
+        params: Value,
+    ) -> Result<Value, UpdateOutcome> {
+        let Api::Tencent {
+            secret_id,
+            secret_key,
+        } = &self.api
+        else {
+            unreachable!("TencentCloud call with a legacy token");
+        };
+        let payload = params.to_string();
+        let now = Utc::now();
+        let authorization = tc3_authorization(secret_id, secret_key, &self.endpoint, &payload, now);
+
+        let resp = client
+            .post(format!("https://{}/", self.endpoint))
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/json; charset=utf-8",
+            )
+            .header(reqwest::header::AUTHORIZATION, authorization)
+            .header("X-TC-Action", action)
+            .header("X-TC-Version", TENCENT_VERSION)
+            .header("X-TC-Timestamp", now.timestamp().to_string())
+            .body(payload)
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return Err(outcome);
+        }
+        let body: Value = resp
+            .json()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(format!("unexpected response: {}", e)))?;
+
+        let response = &body["Response"];
+        let Some(error) = response.get("Error") else {
+            return Ok(response.clone());
+        };
+        let code = error["Code"].as_str().unwrap_or_default();
+        let message = format!("{} {}", code, error["Message"].as_str().unwrap_or_default());
+        if code.starts_with("AuthFailure") || code == "UnauthorizedOperation" {
+            Err(UpdateOutcome::AuthFailed(message))
+        } else {
+            Err(UpdateOutcome::Failed(message))
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for DnsPod {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+
+        let record = match self.find_record(client, record_type).await {
+            Ok(record) => record,
+            Err(outcome) => return outcome,
+        };
+        if record.as_ref().is_some_and(|r| r.value == ip.to_string()) {
+            return UpdateOutcome::NoChange;
+        }
+
+        let record_id = record.as_ref().map(|r| r.id.as_str());
+        match self.write_record(client, record_type, ip, record_id).await {
+            Ok(_) => UpdateOutcome::Good,
+            Err(outcome) => outcome,
+        }
+    }
+}
+
+// Builds the Authorization header of a TencentCloud API v3 request
+fn tc3_authorization(
+    secret_id: &str,
+    secret_key: &str,
+    host: &str,
+    payload: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let date = now.format("%Y-%m-%d").to_string();
+    let service = host.split('.').next().unwrap_or_default();
+    let scope = format!("{}/{}/tc3_request", date, service);
+
+    let canonical_request = format!(
+        "POST\n/\n\ncontent-type:application/json; charset=utf-8\nhost:{}\n\ncontent-type;host\n{}",
+        host,
+        hex(&Sha256::digest(payload.as_bytes()))
+    );
+    let string_to_sign = format!(
+        "TC3-HMAC-SHA256\n{}\n{}\n{}",
+        now.timestamp(),
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = [date.as_str(), service, "tc3_request"]
+        .iter()
+        .fold(format!("TC3{}", secret_key).into_bytes(), |key, part| {
+            hmac_sha256(&key, part)
+        });
+    format!(
+        "TC3-HMAC-SHA256 Credential={}/{}, SignedHeaders=content-type;host, Signature={}",
+        secret_id,
+        scope,
+        hex(&hmac_sha256(&key, &string_to_sign))
+    )
+}
+
+// IDs and codes come as strings or numbers depending on the API
+fn value_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn tc3_authorization_matches_the_tencent_example() {
+        // The signing example of the TencentCloud API v3 documentation
+        let payload = r#"{"Limit": 1, "Filters": [{"Values": ["\u672a\u547d\u540d"], "Name": "instance-name"}]}"#;
+        let now = Utc.timestamp_opt(1551113065, 0).unwrap();
+        assert_eq!(
+            tc3_authorization(
+                "AKIDz8krbsJ5yKBZQpn74WFkmLPx3EXAMPLE",
+                "Gu5t9xGARNpq86cd98joQYCN3EXAMPLE",
+                "cvm.tencentcloudapi.com",
+                payload,
+                now
+            ),
+            "TC3-HMAC-SHA256 Credential=AKIDz8krbsJ5yKBZQpn74WFkmLPx3EXAMPLE/2019-02-25/cvm/tc3_request, SignedHeaders=content-type;host, Signature=72e494ea809ad7a8c8f7a4507b9bddcbaa8e581f516e8da2f66e2c5a96525168"
+        );
+    }
+}
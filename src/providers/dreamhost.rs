@@ -0,0 +1,182 @@
+use super::{request_error, status_outcome, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use log::{error, warn};
+use serde::Deserialize;
+use serde_json::Value;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const API: &str = "api.dreamhost.com";
+
+// DreamHost cannot edit a record, so an update removes the old record and adds
+// the new one. If adding fails, the old record is put back so the name does
+// not stay without an address.
+struct DreamHost {
+    api: String,
+    key: String,
+    hostname: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    result: String,
+    #[serde(default)]
+    data: Value,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    record: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    value: String,
+    #[serde(default)]
+    editable: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err("token (API key) is required by the dreamhost provider".to_string());
+    }
+    if host.hostname.is_empty() {
+        return Err("hostname is required by the dreamhost provider".to_string());
+    }
+
+    Ok(Arc::new(DreamHost {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        key: host.token.clone(),
+        hostname: host.hostname.clone(),
+    }))
+}
+
+impl DreamHost {
+    // Runs one API command; DreamHost reports errors in the body with a 200
+    async fn command(
+        &self,
+        client: &reqwest::Client,
+        cmd: &str,
+        params: &[(&str, &str)],
+    ) -> Result<Value, UpdateOutcome> {
+        let resp = client
+            .get(format!("https://{}/", self.api))
+            .query(&[("key", self.key.as_str()), ("cmd", cmd), ("format", "json")])
+            .query(params)
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return Err(outcome);
+        }
+
+        let body: Response = resp
+            .json()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(format!("unexpected response: {}", e)))?;
+        if body.result == "success" {
+            return Ok(body.data);
+        }
+
+        let message = format!(
+            "{} failed: {}",
+            cmd,
+            body.data.as_str().unwrap_or(&body.data.to_string())
+        );
+        if message.contains("key") {
+            Err(UpdateOutcome::AuthFailed(message))
+        } else {
+            Err(UpdateOutcome::Failed(message))
+        }
+    }
+
+    async fn add(
+        &self,
+        client: &reqwest::Client,
+        record_type: &str,
+        value: &str,
+    ) -> Result<Value, UpdateOutcome> {
+        self.command(
+            client,
+            "dns-add_record",
+            &[
+                ("record", &self.hostname),
+                ("type", record_type),
+                ("value", value),
+                ("comment", "ddns-updater"),
+            ],
+        )
+        .await
+    }
+
+    // Puts removed records back after a failed update
+    async fn restore(&self, client: &reqwest::Client, record_type: &str, values: &[&str]) {
+        for value in values {
+            match self.add(client, record_type, value).await {
+                Ok(_) => warn!(
+                    "⚠ Update of {} failed - restored its previous {} record {}",
+                    self.hostname, record_type, value
+                ),
+                Err(_) => error!(
+                    "✗ Could not restore the {} record {} of {} - add it back in the DreamHost panel",
+                    record_type, value, self.hostname
+                ),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for DreamHost {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let value = ip.to_string();
+
+        let records = match self.command(client, "dns-list_records", &[]).await {
+            Ok(data) => match serde_json::from_value::<Vec<Record>>(data) {
+                Ok(records) => records,
+                Err(e) => return UpdateOutcome::Failed(format!("unexpected response: {}", e)),
+            },
+            Err(outcome) => return outcome,
+        };
+        let existing: Vec<Record> = records
+            .into_iter()
+            .filter(|r| r.record == self.hostname && r.record_type == record_type)
+            .collect();
+
+        if existing.iter().any(|r| r.value == value) {
+            return UpdateOutcome::NoChange;
+        }
+        if let Some(record) = existing.iter().find(|r| r.editable == "0") {
+            return UpdateOutcome::Failed(format!(
+                "the {} record {} is managed by DreamHost and cannot be changed",
+                record_type, record.value
+            ));
+        }
+
+        let mut removed = Vec::new();
+        for record in &existing {
+            let params = [
+                ("record", self.hostname.as_str()),
+                ("type", record_type),
+                ("value", record.value.as_str()),
+            ];
+            if let Err(outcome) = self.command(client, "dns-remove_record", &params).await {
+                self.restore(client, record_type, &removed).await;
+                return outcome;
+            }
+            removed.push(record.value.as_str());
+        }
+
+        match self.add(client, record_type, &value).await {
+            Ok(_) => UpdateOutcome::Good,
+            Err(outcome) => {
+                self.restore(client, record_type, &removed).await;
+                outcome
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use super::{request_error, status_outcome, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const ENDPOINT: &str = "www.duckdns.org/update";
+
+// DuckDNS answers every request with 200 and a plain `OK` or `KO` body
+struct DuckDns {
+    endpoint: String,
+    domain: String,
+    token: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err("token is required by the duckdns provider".to_string());
+    }
+    // Accepts both `myhost` and `myhost.duckdns.org`
+    let domain = host.hostname.trim_end_matches(".duckdns.org");
+    if domain.is_empty() {
+        return Err("hostname is required by the duckdns provider".to_string());
+    }
+
+    Ok(Arc::new(DuckDns {
+        endpoint: if host.ddns.is_empty() {
+            ENDPOINT.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        domain: domain.to_string(),
+        token: host.token.clone(),
+    }))
+}
+
+#[async_trait]
+impl Provider for DuckDns {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let param = if ip.is_ipv4() { "ip" } else { "ipv6" };
+        let url = format!("https://{}", self.endpoint);
+        let request = client.get(url).query(&[
+            ("domains", self.domain.as_str()),
+            ("token", self.token.as_str()),
+            (param, &ip.to_string()),
+        ]);
+
+        let resp = match request.send().await {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return outcome;
+        }
+
+        match resp.text().await.as_deref().map(str::trim) {
+            Ok("OK") => UpdateOutcome::Good,
+            Ok("KO") => {
+                UpdateOutcome::AuthFailed("KO - DuckDNS rejected the token or domain".to_string())
+            }
+            Ok(body) => UpdateOutcome::Failed(format!("unexpected response: {}", body)),
+            Err(e) => UpdateOutcome::Failed(format!("failed to read response: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,808 @@
+use super::{
+    percent_encode, request_error, split_hostname, status_outcome, Provider, UpdateOutcome,
+};
+use crate::oauth2::TokenManager;
+use crate::HostConfig;
+use async_trait::async_trait;
+use log::warn;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+// DynDNS2 providers report most failures with a 200 status and a return code
+// in the body, so the body is always parsed. Presets fill in the endpoint and
+// treat unknown bodies as errors; the plain `dyndns2` provider accepts them,
+// since custom endpoints may answer with anything on success.
+struct Preset {
+    name: &'static str,
+    endpoint: Option<&'static str>,
+    requires_hostname: bool,
+    strict_response: bool,
+    refresh_interval: Option<Duration>,
+    // Documented minimum seconds between checks; updating more often risks
+    // the account being flagged for abuse
+    min_interval: Option<u64>,
+    supports_ipv6: bool,
+    // What the provider expects as the username when `user` is left out
+    default_user: DefaultUser,
+    // `abuse` locks the host for good, rather than being a short rate limit
+    abuse_blocks: bool,
+    // Parameter for the IPv6 address when the provider takes both families in
+    // one request; each update then also carries the last address sent for
+    // the other family, see `query`
+    ipv6_param: Option<&'static str>,
+    // The body has one return code per line, for each service the update
+    // was passed on to
+    per_line: bool,
+    // Maps the body to an outcome, given `strict_response`
+    parse: fn(&str, bool) -> UpdateOutcome,
+}
+
+enum DefaultUser {
+    None,
+    Hostname,
+    // The zone of `hostname`, e.g. `example.org` for `home.example.org`
+    Domain,
+}
+
+const DYNDNS2: Preset = Preset {
+    name: "dyndns2",
+    endpoint: None,
+    requires_hostname: false,
+    strict_response: false,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::None,
+    abuse_blocks: true,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+// Google Domains, now served by Squarespace for migrated domains. Each record
+// has its own generated username and password.
+const GOOGLE: Preset = Preset {
+    name: "google",
+    endpoint: Some("domains.google.com/nic/update"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::None,
+    abuse_blocks: true,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+// dy.fi deletes hosts that were not updated for 7 days, so the record is
+// re-registered well before that even if the IP did not change. It only
+// manages A records.
+const DYFI: Preset = Preset {
+    name: "dyfi",
+    endpoint: Some("www.dy.fi/nic/update"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: Some(Duration::from_secs(5 * 24 * 60 * 60)),
+    min_interval: None,
+    supports_ipv6: false,
+    default_user: DefaultUser::None,
+    abuse_blocks: true,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+// No-IP rejects hosts that keep updating after an `abuse` response
+const NOIP: Preset = Preset {
+    name: "noip",
+    endpoint: Some("dynupdate.no-ip.com/nic/update"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: Some(300),
+    supports_ipv6: true,
+    default_user: DefaultUser::None,
+    abuse_blocks: true,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+// OVH DynHost answers 200 even when it rejects an update, so the body has to be
+// checked. DynHost only manages A records.
+const OVH: Preset = Preset {
+    name: "ovh",
+    endpoint: Some("www.ovh.com/nic/update"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: false,
+    default_user: DefaultUser::None,
+    abuse_blocks: true,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+// Hurricane Electric authenticates each record with its own generated key, sent
+// as the password with the record name as the username
+const HE: Preset = Preset {
+    name: "he",
+    endpoint: Some("dyn.dns.he.net/nic/update"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::Hostname,
+    abuse_blocks: true,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+// Strato logs in with the domain, not the record name, and answers `abuse` to
+// clients that update too often; the block lifts after a few minutes.
+const STRATO: Preset = Preset {
+    name: "strato",
+    endpoint: Some("dyndns.strato.com/nic/update"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::Domain,
+    abuse_blocks: false,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+// INWX uses dedicated DynDNS credentials, separate from the account login
+const INWX: Preset = Preset {
+    name: "inwx",
+    endpoint: Some("dyndns.inwx.com/nic/update"),
+    requires_hostname: false,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::None,
+    abuse_blocks: true,
+    ipv6_param: Some("myipv6"),
+    per_line: false,
+    parse: parse_response,
+};
+
+// DNS-O-Matic passes each update on to the services configured in the account
+// (or only to `hostname`'s if set) and returns one line per service. A
+// downstream `abuse` must not lock out all the other services.
+const DNSOMATIC: Preset = Preset {
+    name: "dnsomatic",
+    endpoint: Some("updates.dnsomatic.com/nic/update"),
+    requires_hostname: false,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::None,
+    abuse_blocks: false,
+    ipv6_param: None,
+    per_line: true,
+    parse: parse_response,
+};
+
+// Loopia throttles clients that update too often with `abuse`, which lifts
+// again after a while
+const LOOPIA: Preset = Preset {
+    name: "loopia",
+    endpoint: Some("dyndns.loopia.se"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: Some(300),
+    supports_ipv6: true,
+    default_user: DefaultUser::None,
+    abuse_blocks: false,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+// easyDNS takes DynDNS2-style requests but answers with its own codes, and
+// rejects updates less than 10 minutes apart
+const EASYDNS: Preset = Preset {
+    name: "easydns",
+    endpoint: Some("api.cp.easydns.com/dyn/generic.php"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: Some(600),
+    supports_ipv6: true,
+    default_user: DefaultUser::None,
+    abuse_blocks: false,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_easydns_response,
+};
+
+// SPDYN accepts either the account login or, per host, the hostname with the
+// host's update token, so `user` defaults to `hostname`
+const SPDYN: Preset = Preset {
+    name: "spdyn",
+    endpoint: Some("update.spdyn.de/nic/update"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::Hostname,
+    abuse_blocks: true,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_spdyn_response,
+};
+
+// dnsHome.de logs in with the subdomain and its password, and takes the IPv6
+// address in `ip6` next to `myip`
+const DNSHOME: Preset = Preset {
+    name: "dnshome",
+    endpoint: Some("www.dnshome.de/dyndns.php"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::Hostname,
+    abuse_blocks: true,
+    ipv6_param: Some("ip6"),
+    per_line: false,
+    parse: parse_response,
+};
+
+// Domain-Offensive FlexDNS authenticates with the generated DDNS login and its
+// token; the record is tied to the login, so `hostname` is optional
+const DODE: Preset = Preset {
+    name: "dode",
+    endpoint: Some("ddns.do.de/"),
+    requires_hostname: false,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::None,
+    abuse_blocks: true,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+// Variomedia takes the account email with either the account password or a
+// dynamic DNS token as the password
+const VARIOMEDIA: Preset = Preset {
+    name: "variomedia",
+    endpoint: Some("dyndns.variomedia.de/nic/update"),
+    requires_hostname: true,
+    strict_response: true,
+    refresh_interval: None,
+    min_interval: None,
+    supports_ipv6: true,
+    default_user: DefaultUser::None,
+    abuse_blocks: true,
+    ipv6_param: None,
+    per_line: false,
+    parse: parse_response,
+};
+
+const PRESETS: &[&Preset] = &[
+    &DYNDNS2,
+    &GOOGLE,
+    &DYFI,
+    &NOIP,
+    &OVH,
+    &HE,
+    &STRATO,
+    &INWX,
+    &DNSOMATIC,
+    &LOOPIA,
+    &EASYDNS,
+    &SPDYN,
+    &DNSHOME,
+    &DODE,
+    &VARIOMEDIA,
+];
+
+// Documented minimum check interval of a DynDNS2 preset
+pub fn preset_min_interval(name: &str) -> Option<u64> {
+    PRESETS
+        .iter()
+        .find(|preset| preset.name == name)
+        .and_then(|preset| preset.min_interval)
+}
+
+pub fn dyndns2(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &DYNDNS2)
+}
+
+pub fn google(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &GOOGLE)
+}
+
+pub fn dyfi(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &DYFI)
+}
+
+pub fn noip(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &NOIP)
+}
+
+pub fn ovh(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &OVH)
+}
+
+pub fn he(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &HE)
+}
+
+pub fn strato(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &STRATO)
+}
+
+pub fn inwx(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &INWX)
+}
+
+pub fn dnsomatic(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &DNSOMATIC)
+}
+
+pub fn loopia(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &LOOPIA)
+}
+
+pub fn easydns(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &EASYDNS)
+}
+
+pub fn spdyn(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &SPDYN)
+}
+
+pub fn dnshome(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &DNSHOME)
+}
+
+pub fn dode(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &DODE)
+}
+
+pub fn variomedia(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    DynDns2::build(host, &VARIOMEDIA)
+}
+
+struct DynDns2 {
+    preset: &'static Preset,
+    endpoint: String,
+    user: String,
+    pass: String,
+    hostname: String,
+    token_manager: Option<TokenManager>,
+    last_v4: Mutex<Option<IpAddr>>,
+    last_v6: Mutex<Option<IpAddr>>,
+}
+
+impl DynDns2 {
+    fn build(host: &HostConfig, preset: &'static Preset) -> Result<Arc<dyn Provider>, String> {
+        let endpoint = if host.ddns.is_empty() {
+            preset.endpoint.unwrap_or_default().to_string()
+        } else {
+            host.ddns.clone()
+        };
+        if endpoint.is_empty() {
+            return Err("ddns is missing".to_string());
+        }
+
+        if preset.requires_hostname && host.hostname.is_empty() {
+            return Err(format!(
+                "hostname is required by the {} provider",
+                preset.name
+            ));
+        }
+
+        let user = match preset.default_user {
+            _ if !host.user.is_empty() => host.user.clone(),
+            DefaultUser::None => String::new(),
+            DefaultUser::Hostname => host.hostname.clone(),
+            DefaultUser::Domain => split_hostname(&host.hostname, &host.zone)?.1,
+        };
+        match &host.oauth2 {
+            Some(oauth2) if !oauth2.is_valid() => {
+                return Err("oauth2 needs token_url, client_id and client_secret".to_string())
+            }
+            None if user.is_empty() || host.pass.is_empty() => {
+                return Err("user or pass is missing".to_string())
+            }
+            _ => {}
+        }
+
+        Ok(Arc::new(DynDns2 {
+            preset,
+            endpoint,
+            user,
+            pass: host.pass.clone(),
+            hostname: host.hostname.clone(),
+            token_manager: host.oauth2.clone().map(TokenManager::new),
+            last_v4: Mutex::new(None),
+            last_v6: Mutex::new(None),
+        }))
+    }
+}
+
+// Query string of an update; `other` is the address last sent for the other
+// family. Providers that take both families at once get `myip` even when no
+// IPv4 address is known yet (IPv6-only hosts, the first update after a start):
+// without it they fill the A record from the request's source address, which
+// may be a record the user never asked to manage. It is sent empty then.
+fn query(preset: &Preset, hostname: &str, ip: IpAddr, other: Option<IpAddr>) -> String {
+    let mut params = Vec::new();
+    if !hostname.is_empty() {
+        params.push(format!("hostname={}", percent_encode(hostname)));
+    }
+    match preset.ipv6_param {
+        Some(ipv6_param) => {
+            let (v4, v6) = if ip.is_ipv4() {
+                (Some(ip), other)
+            } else {
+                (other, Some(ip))
+            };
+            params.push(format!(
+                "myip={}",
+                v4.map(|v4| v4.to_string()).unwrap_or_default()
+            ));
+            if let Some(v6) = v6 {
+                params.push(format!("{}={}", ipv6_param, v6));
+            }
+        }
+        None => params.push(format!("myip={}", ip)),
+    }
+    params.join("&")
+}
+
+#[async_trait]
+impl Provider for DynDns2 {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let other = if ip.is_ipv4() {
+            *self.last_v6.lock().await
+        } else {
+            *self.last_v4.lock().await
+        };
+        let query = query(self.preset, &self.hostname, ip, other);
+
+        // Credentials go in the header rather than the URL, where an `@`, `:` or
+        // `/` in them would break it
+        let request = client.get(format!("https://{}?{}", self.endpoint, query));
+        let request = match &self.token_manager {
+            Some(manager) => match manager.token(client).await {
+                Ok(token) => request.bearer_auth(token),
+                Err(e) => return UpdateOutcome::AuthFailed(e.to_string()),
+            },
+            None => request.basic_auth(&self.user, Some(&self.pass)),
+        };
+        // Google rejects requests without a user agent with `badagent`
+        let request = request.header(
+            reqwest::header::USER_AGENT,
+            concat!("ddns-updater/", env!("CARGO_PKG_VERSION")),
+        );
+
+        let resp = match request.send().await {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return outcome;
+        }
+
+        let outcome = match resp.text().await {
+            Ok(body) if self.preset.per_line => parse_lines(&body),
+            Ok(body) => (self.preset.parse)(&body, self.preset.strict_response),
+            Err(e) => UpdateOutcome::Failed(format!("failed to read response: {}", e)),
+        };
+        let outcome = match outcome {
+            UpdateOutcome::Blocked(e) if !self.preset.abuse_blocks => {
+                UpdateOutcome::Failed(format!("{} (rate limited, retrying later)", e))
+            }
+            outcome => outcome,
+        };
+        if matches!(outcome, UpdateOutcome::Good | UpdateOutcome::NoChange) {
+            let last = if ip.is_ipv4() {
+                &self.last_v4
+            } else {
+                &self.last_v6
+            };
+            *last.lock().await = Some(ip);
+        }
+        outcome
+    }
+
+    async fn invalidate(&self) -> bool {
+        match &self.token_manager {
+            Some(manager) => {
+                manager.invalidate().await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn refresh_interval(&self) -> Option<Duration> {
+        self.preset.refresh_interval
+    }
+
+    fn supports_ipv6(&self) -> bool {
+        self.preset.supports_ipv6
+    }
+}
+
+// Maps a DynDNS2 response body to an outcome. Known failure codes are always
+// reported; unknown bodies only count as failures when `strict` is set.
+pub(super) fn parse_response(body: &str, strict: bool) -> UpdateOutcome {
+    let code = body.split_whitespace().next().unwrap_or_default();
+    let message = match code {
+        "good" => return UpdateOutcome::Good,
+        "nochg" => return UpdateOutcome::NoChange,
+        "badauth" => {
+            return UpdateOutcome::AuthFailed(
+                "badauth - check the credentials for this record".to_string(),
+            )
+        }
+        "nohost" => "nohost - hostname does not exist or has no dynamic DNS enabled",
+        "notfqdn" => "notfqdn - hostname is not a fully qualified domain name",
+        "badagent" => "badagent - request was rejected as coming from a bad user agent",
+        "abuse" => {
+            return UpdateOutcome::Blocked(
+                "abuse - the provider blocked updates for this host".to_string(),
+            )
+        }
+        "!donator" => "!donator - this update needs a paid account",
+        "conflict" => "conflict - a custom A/AAAA record exists for this hostname",
+        "dnserr" => "dnserr - provider-side DNS error, retrying later",
+        "911" => "911 - provider-side error, retrying later",
+        "badrequest" => "badrequest - provider rejected the update parameters",
+        _ if !strict => {
+            if !body.trim().is_empty() {
+                warn!(
+                    "⚠ Unrecognized DynDNS2 response '{}' - treating it as success",
+                    body.trim()
+                );
+            }
+            return UpdateOutcome::Good;
+        }
+        "" => "empty response from provider",
+        _ => return UpdateOutcome::Failed(format!("unexpected response: {}", body.trim())),
+    };
+    UpdateOutcome::Failed(message.to_string())
+}
+
+// Combines the return codes of a multi-line response into the most severe
+// outcome; failures name how many of the services reported them.
+fn parse_lines(body: &str) -> UpdateOutcome {
+    let outcomes: Vec<UpdateOutcome> = body
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| parse_response(line, true))
+        .collect();
+    if outcomes.is_empty() {
+        return UpdateOutcome::Failed("empty response from provider".to_string());
+    }
+
+    let failures: Vec<&UpdateOutcome> = outcomes
+        .iter()
+        .filter(|o| !matches!(o, UpdateOutcome::Good | UpdateOutcome::NoChange))
+        .collect();
+    let Some(worst) = failures
+        .iter()
+        .max_by_key(|o| match o {
+            UpdateOutcome::AuthFailed(_) => 2,
+            UpdateOutcome::Blocked(_) => 1,
+            _ => 0,
+        })
+        .copied()
+    else {
+        return if outcomes.contains(&UpdateOutcome::Good) {
+            UpdateOutcome::Good
+        } else {
+            UpdateOutcome::NoChange
+        };
+    };
+
+    let mut messages: Vec<&str> = Vec::new();
+    for failure in &failures {
+        if let UpdateOutcome::AuthFailed(e) | UpdateOutcome::Failed(e) | UpdateOutcome::Blocked(e) =
+            failure
+        {
+            if !messages.contains(&e.as_str()) {
+                messages.push(e);
+            }
+        }
+    }
+    let message = format!(
+        "{} of {} services failed: {}",
+        failures.len(),
+        outcomes.len(),
+        messages.join("; ")
+    );
+    match worst {
+        UpdateOutcome::AuthFailed(_) => UpdateOutcome::AuthFailed(message),
+        UpdateOutcome::Blocked(_) => UpdateOutcome::Blocked(message),
+        _ => UpdateOutcome::Failed(message),
+    }
+}
+
+fn parse_easydns_response(body: &str, _strict: bool) -> UpdateOutcome {
+    let code = body.split_whitespace().next().unwrap_or_default();
+    let message = match code {
+        "NOERROR" => return UpdateOutcome::Good,
+        "NOACCESS" => {
+            return UpdateOutcome::AuthFailed("NOACCESS - check the username and token".to_string())
+        }
+        "NOSERVICE" => "NOSERVICE - dynamic DNS is not enabled for this domain",
+        "ILLEGAL" => "ILLEGAL INPUT - easyDNS rejected the update parameters",
+        "TOOSOON" => "TOOSOON - updates must be at least 10 minutes apart, retrying later",
+        "" => "empty response from provider",
+        _ => return UpdateOutcome::Failed(format!("unexpected response: {}", body.trim())),
+    };
+    UpdateOutcome::Failed(message.to_string())
+}
+
+// SPDYN adds a few codes of its own to the DynDNS2 set
+fn parse_spdyn_response(body: &str, strict: bool) -> UpdateOutcome {
+    let code = body.split_whitespace().next().unwrap_or_default();
+    let message = match code {
+        "!yours" => {
+            return UpdateOutcome::AuthFailed(
+                "!yours - hostname belongs to another account".to_string(),
+            )
+        }
+        "numhost" => "numhost - too many hosts in one request",
+        "fatal" => "fatal - the host is disabled in the SPDYN panel",
+        _ => return parse_response(body, strict),
+    };
+    UpdateOutcome::Failed(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_carries_both_families() {
+        let v4: IpAddr = "203.0.113.7".parse().unwrap();
+        let v6: IpAddr = "2001:db8::7".parse().unwrap();
+        let cases = [
+            // (preset, ip, other, query)
+            (
+                &DYNDNS2,
+                v6,
+                None,
+                "hostname=home.example.org&myip=2001:db8::7",
+            ),
+            (
+                &INWX,
+                v4,
+                None,
+                "hostname=home.example.org&myip=203.0.113.7",
+            ),
+            (
+                &INWX,
+                v4,
+                Some(v6),
+                "hostname=home.example.org&myip=203.0.113.7&myipv6=2001:db8::7",
+            ),
+            (
+                &INWX,
+                v6,
+                Some(v4),
+                "hostname=home.example.org&myip=203.0.113.7&myipv6=2001:db8::7",
+            ),
+            // IPv6-only hosts must not leave the A record to the source address
+            (
+                &INWX,
+                v6,
+                None,
+                "hostname=home.example.org&myip=&myipv6=2001:db8::7",
+            ),
+            (
+                &DNSHOME,
+                v6,
+                None,
+                "hostname=home.example.org&myip=&ip6=2001:db8::7",
+            ),
+        ];
+        for (preset, ip, other, expected) in cases {
+            assert_eq!(
+                query(preset, "home.example.org", ip, other),
+                expected,
+                "{}",
+                preset.name
+            );
+        }
+    }
+
+    #[test]
+    fn parse_response_maps_return_codes() {
+        let cases = [
+            // (body, strict, outcome)
+            ("good 203.0.113.7", true, UpdateOutcome::Good),
+            ("nochg 203.0.113.7\n", true, UpdateOutcome::NoChange),
+            (
+                "badauth",
+                true,
+                UpdateOutcome::AuthFailed(
+                    "badauth - check the credentials for this record".to_string(),
+                ),
+            ),
+            (
+                "abuse",
+                true,
+                UpdateOutcome::Blocked(
+                    "abuse - the provider blocked updates for this host".to_string(),
+                ),
+            ),
+            (
+                "nohost",
+                false,
+                UpdateOutcome::Failed(
+                    "nohost - hostname does not exist or has no dynamic DNS enabled".to_string(),
+                ),
+            ),
+            (
+                "<html>ok</html>",
+                true,
+                UpdateOutcome::Failed("unexpected response: <html>ok</html>".to_string()),
+            ),
+            ("<html>ok</html>", false, UpdateOutcome::Good),
+            (
+                "",
+                true,
+                UpdateOutcome::Failed("empty response from provider".to_string()),
+            ),
+            ("", false, UpdateOutcome::Good),
+        ];
+        for (body, strict, outcome) in cases {
+            assert_eq!(parse_response(body, strict), outcome, "{:?}", body);
+        }
+    }
+
+    #[test]
+    fn parse_lines_reports_the_worst_service() {
+        assert_eq!(
+            parse_lines("good 203.0.113.7\nnochg 203.0.113.7"),
+            UpdateOutcome::Good
+        );
+        assert_eq!(parse_lines("nochg\n\nnochg\n"), UpdateOutcome::NoChange);
+        assert_eq!(
+            parse_lines("good\nabuse\nbadauth\nbadauth"),
+            UpdateOutcome::AuthFailed(
+                "3 of 4 services failed: abuse - the provider blocked updates for this host; badauth - check the credentials for this record"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            parse_lines("good\nabuse"),
+            UpdateOutcome::Blocked(
+                "1 of 2 services failed: abuse - the provider blocked updates for this host"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            parse_lines(" \n"),
+            UpdateOutcome::Failed("empty response from provider".to_string())
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use super::{api_error, request_error, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use ipnet::Ipv6Net;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const ENDPOINT: &str = "dynv6.com/api/update";
+
+// Overrides for the parameters of a dynv6 update. `ipv4` and `ipv6` replace
+// the detected address (e.g. `auto` lets dynv6 use the request's source
+// address). `ipv6prefix` is sent with IPv6 updates so dynv6 can rewrite the
+// zone's other records: an explicit prefix, `auto`, or just a length like
+// `/56` to take that prefix of the detected address.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Dynv6Config {
+    #[serde(default)]
+    pub ipv4: Option<String>,
+    #[serde(default)]
+    pub ipv6: Option<String>,
+    #[serde(default)]
+    pub ipv6prefix: Option<String>,
+}
+
+// Parameters left out of the request keep their current value, so A and AAAA
+// are updated independently.
+struct Dynv6 {
+    endpoint: String,
+    zone: String,
+    token: String,
+    options: Dynv6Config,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err("token (HTTP token) is required by the dynv6 provider".to_string());
+    }
+    if host.hostname.is_empty() {
+        return Err("hostname (the dynv6 zone) is required by the dynv6 provider".to_string());
+    }
+    let options = host.dynv6.clone().unwrap_or_default();
+    if let Some(length) = options
+        .ipv6prefix
+        .as_deref()
+        .and_then(|prefix| prefix.strip_prefix('/'))
+    {
+        if !matches!(length.parse::<u8>(), Ok(0..=128)) {
+            return Err(format!("invalid dynv6 ipv6prefix length '/{}'", length));
+        }
+    }
+
+    Ok(Arc::new(Dynv6 {
+        endpoint: if host.ddns.is_empty() {
+            ENDPOINT.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        zone: host.hostname.clone(),
+        token: host.token.clone(),
+        options,
+    }))
+}
+
+#[async_trait]
+impl Provider for Dynv6 {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let mut query = vec![("zone", self.zone.clone()), ("token", self.token.clone())];
+        match ip {
+            IpAddr::V4(_) => {
+                query.push(("ipv4", self.options.ipv4.clone().unwrap_or(ip.to_string())));
+            }
+            IpAddr::V6(v6) => {
+                query.push(("ipv6", self.options.ipv6.clone().unwrap_or(ip.to_string())));
+                let prefix = match self.options.ipv6prefix.as_deref() {
+                    Some(prefix) => match prefix.strip_prefix('/').map(str::parse) {
+                        Some(Ok(length)) => Ipv6Net::new(v6, length)
+                            .map(|net| net.trunc().to_string())
+                            .ok(),
+                        _ => Some(prefix.to_string()),
+                    },
+                    None => None,
+                };
+                if let Some(prefix) = prefix {
+                    query.push(("ipv6prefix", prefix));
+                }
+            }
+        }
+
+        let resp = match client
+            .get(format!("https://{}", self.endpoint))
+            .query(&query)
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        let body = body.trim();
+        if !status.is_success() {
+            return api_error(status, (!body.is_empty()).then(|| body.to_string()));
+        }
+        match body {
+            "addresses updated" => UpdateOutcome::Good,
+            "addresses unchanged" => UpdateOutcome::NoChange,
+            _ => UpdateOutcome::Failed(format!("unexpected response: {}", body)),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use super::{request_error, status_outcome, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const ENDPOINT: &str = "freedns.afraid.org/dynamic/update.php";
+
+// Every FreeDNS record has its own update token, passed as the bare query
+// string. Responses are plain sentences with a 200 status.
+struct FreeDns {
+    endpoint: String,
+    token: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err(
+            "token (the record's update token) is required by the freedns provider".to_string(),
+        );
+    }
+
+    Ok(Arc::new(FreeDns {
+        endpoint: if host.ddns.is_empty() {
+            ENDPOINT.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        token: host.token.clone(),
+    }))
+}
+
+#[async_trait]
+impl Provider for FreeDns {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let url = format!("https://{}?{}&address={}", self.endpoint, self.token, ip);
+        let resp = match client.get(url).send().await {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return outcome;
+        }
+
+        match resp.text().await {
+            Ok(body) => parse_response(body.trim()),
+            Err(e) => UpdateOutcome::Failed(format!("failed to read response: {}", e)),
+        }
+    }
+}
+
+fn parse_response(body: &str) -> UpdateOutcome {
+    if body.starts_with("Updated") {
+        UpdateOutcome::Good
+    } else if body.contains("has not changed") || body.starts_with("No IP change detected") {
+        UpdateOutcome::NoChange
+    } else if body.contains("Unable to locate this record") || body.contains("Invalid update URL") {
+        UpdateOutcome::AuthFailed(format!("{} - check the update token", body))
+    } else if body.is_empty() {
+        UpdateOutcome::Failed("empty response from provider".to_string())
+    } else {
+        UpdateOutcome::Failed(format!("unexpected response: {}", body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_response_maps_messages() {
+        let cases = [
+            (
+                "Updated 1 host(s) home.example.org to 203.0.113.7 in 0.21 seconds",
+                UpdateOutcome::Good,
+            ),
+            (
+                "ERROR: Address 203.0.113.7 has not changed.",
+                UpdateOutcome::NoChange,
+            ),
+            (
+                "No IP change detected for home.example.org",
+                UpdateOutcome::NoChange,
+            ),
+            (
+                "ERROR: Unable to locate this record",
+                UpdateOutcome::AuthFailed(
+                    "ERROR: Unable to locate this record - check the update token".to_string(),
+                ),
+            ),
+            (
+                "",
+                UpdateOutcome::Failed("empty response from provider".to_string()),
+            ),
+            (
+                "ERROR: something else",
+                UpdateOutcome::Failed("unexpected response: ERROR: something else".to_string()),
+            ),
+        ];
+        for (body, outcome) in cases {
+            assert_eq!(parse_response(body), outcome, "{}", body);
+        }
+    }
+}
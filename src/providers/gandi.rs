@@ -0,0 +1,79 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const API: &str = "api.gandi.net/v5/livedns";
+
+// LiveDNS replaces the whole rrset with a single PUT, so there is no need to
+// look up the existing record first.
+struct Gandi {
+    api: String,
+    token: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err("token (personal access token) is required by the gandi provider".to_string());
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Gandi {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        token: host.token.clone(),
+        zone,
+        // The zone apex is `@` in LiveDNS
+        name: if name.is_empty() {
+            "@".to_string()
+        } else {
+            name
+        },
+        ttl: host.ttl,
+    }))
+}
+
+#[async_trait]
+impl Provider for Gandi {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let mut body = json!({ "rrset_values": [ip.to_string()] });
+        if let Some(ttl) = self.ttl {
+            body["rrset_ttl"] = json!(ttl);
+        }
+
+        let resp = match client
+            .put(format!(
+                "https://{}/domains/{}/records/{}/{}",
+                self.api, self.zone, self.name, record_type
+            ))
+            .bearer_auth(&self.token)
+            .json(&body)
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+
+        let status = resp.status();
+        if status.is_success() {
+            return UpdateOutcome::Good;
+        }
+
+        let message = resp
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body["message"].as_str().map(str::to_string));
+        api_error(status, message)
+    }
+}
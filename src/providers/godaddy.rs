@@ -0,0 +1,87 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const API: &str = "api.godaddy.com/v1";
+
+// Replacing the records of one name and type is a single PUT, which also
+// creates them if there are none. Several names under one domain are several
+// hosts sharing the same key pair.
+struct GoDaddy {
+    api: String,
+    key: String,
+    secret: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() || host.secret.is_empty() {
+        return Err(
+            "token (API key) and secret (API secret) are required by the godaddy provider"
+                .to_string(),
+        );
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(GoDaddy {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        key: host.token.clone(),
+        secret: host.secret.clone(),
+        zone,
+        name: if name.is_empty() {
+            "@".to_string()
+        } else {
+            name
+        },
+        ttl: host.ttl,
+    }))
+}
+
+#[async_trait]
+impl Provider for GoDaddy {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let mut record = json!({ "data": ip.to_string() });
+        if let Some(ttl) = self.ttl {
+            record["ttl"] = json!(ttl);
+        }
+
+        let resp = match client
+            .put(format!(
+                "https://{}/domains/{}/records/{}/{}",
+                self.api, self.zone, record_type, self.name
+            ))
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("sso-key {}:{}", self.key, self.secret),
+            )
+            .json(&json!([record]))
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+
+        let status = resp.status();
+        if status.is_success() {
+            return UpdateOutcome::Good;
+        }
+
+        let message = resp
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body["message"].as_str().map(str::to_string));
+        api_error(status, message)
+    }
+}
@@ -0,0 +1,184 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const API: &str = "dns.hetzner.com/api/v1";
+
+// The zone ID is looked up by name once and kept for the lifetime of the
+// provider; records are looked up on every update since they may be edited in
+// the DNS Console.
+struct Hetzner {
+    api: String,
+    token: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+    zone_id: Mutex<Option<String>>,
+}
+
+#[derive(Deserialize)]
+struct Zones {
+    zones: Vec<Zone>,
+}
+
+#[derive(Deserialize)]
+struct Zone {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Records {
+    #[serde(default)]
+    records: Vec<Record>,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    id: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err("token (DNS API token) is required by the hetzner provider".to_string());
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Hetzner {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        token: host.token.clone(),
+        zone,
+        name: if name.is_empty() {
+            "@".to_string()
+        } else {
+            name
+        },
+        ttl: host.ttl,
+        zone_id: Mutex::new(None),
+    }))
+}
+
+impl Hetzner {
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, UpdateOutcome> {
+        let resp = request
+            .header("Auth-API-Token", &self.token)
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let message = resp.json::<ApiError>().await.ok().and_then(|e| {
+                e.message.or_else(|| {
+                    e.error
+                        .and_then(|error| error["message"].as_str().map(str::to_string))
+                })
+            });
+            return Err(api_error(status, message));
+        }
+
+        resp.json()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(format!("unexpected response: {}", e)))
+    }
+
+    async fn zone_id(&self, client: &reqwest::Client) -> Result<String, UpdateOutcome> {
+        let mut cached = self.zone_id.lock().await;
+        if let Some(id) = cached.as_ref() {
+            return Ok(id.clone());
+        }
+
+        let zones: Zones = self
+            .send(
+                client
+                    .get(format!("https://{}/zones", self.api))
+                    .query(&[("name", &self.zone)]),
+            )
+            .await?;
+        let Some(zone) = zones.zones.into_iter().find(|z| z.name == self.zone) else {
+            return Err(UpdateOutcome::Failed(format!(
+                "zone '{}' not found in this account",
+                self.zone
+            )));
+        };
+
+        *cached = Some(zone.id.clone());
+        Ok(zone.id)
+    }
+}
+
+#[async_trait]
+impl Provider for Hetzner {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+
+        let zone_id = match self.zone_id(client).await {
+            Ok(id) => id,
+            Err(outcome) => return outcome,
+        };
+
+        let records: Records = match self
+            .send(
+                client
+                    .get(format!("https://{}/records", self.api))
+                    .query(&[("zone_id", &zone_id)]),
+            )
+            .await
+        {
+            Ok(records) => records,
+            Err(outcome) => return outcome,
+        };
+        let existing = records
+            .records
+            .into_iter()
+            .find(|r| r.name == self.name && r.record_type == record_type);
+
+        if existing.as_ref().is_some_and(|r| r.value == ip.to_string()) {
+            return UpdateOutcome::NoChange;
+        }
+
+        let mut body = json!({
+            "zone_id": zone_id,
+            "type": record_type,
+            "name": self.name,
+            "value": ip.to_string(),
+        });
+        if let Some(ttl) = self.ttl {
+            body["ttl"] = json!(ttl);
+        }
+
+        let request = match existing {
+            Some(record) => client.put(format!("https://{}/records/{}", self.api, record.id)),
+            None => client.post(format!("https://{}/records", self.api)),
+        };
+        match self.send::<Value>(request.json(&body)).await {
+            Ok(_) => UpdateOutcome::Good,
+            Err(outcome) => outcome,
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use reqwest::header::{COOKIE, SET_COOKIE};
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const SITE: &str = "www.hover.com";
+// Hover's session cookie; the login only succeeded if it was set
+const AUTH_COOKIE: &str = "hoverauth";
+
+// Hover has no public API. This logs in like the web client, keeps the session
+// cookies and uses the same JSON endpoints to edit or add the record. An
+// expired session (401) is replaced by a fresh login and the request repeated.
+struct Hover {
+    site: String,
+    user: String,
+    pass: String,
+    zone: String,
+    name: String,
+    session: Mutex<Option<String>>,
+}
+
+#[derive(Deserialize)]
+struct Domains {
+    #[serde(default)]
+    domains: Vec<Domain>,
+}
+
+#[derive(Deserialize)]
+struct Domain {
+    #[serde(default)]
+    entries: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.user.is_empty() || host.pass.is_empty() {
+        return Err("user and pass (Hover login) are required by the hover provider".to_string());
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Hover {
+        site: if host.ddns.is_empty() {
+            SITE.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        user: host.user.clone(),
+        pass: host.pass.clone(),
+        zone,
+        // The zone apex is `@` at Hover
+        name: if name.is_empty() {
+            "@".to_string()
+        } else {
+            name
+        },
+        session: Mutex::new(None),
+    }))
+}
+
+impl Hover {
+    // Logs in and returns the session cookies as a `Cookie` header value
+    async fn login(&self, client: &reqwest::Client) -> Result<String, UpdateOutcome> {
+        let resp = client
+            .post(format!("https://{}/signin/auth.json", self.site))
+            .json(&json!({ "username": self.user, "password": self.pass }))
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+
+        let cookies: Vec<String> = resp
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| value.split(';').next())
+            .map(|pair| pair.trim().to_string())
+            .collect();
+        let status = resp.status();
+        if !status.is_success() && status != StatusCode::UNAUTHORIZED {
+            return Err(api_error(status, None));
+        }
+        if !cookies
+            .iter()
+            .any(|c| c.starts_with(&format!("{}=", AUTH_COOKIE)))
+        {
+            return Err(UpdateOutcome::AuthFailed(
+                "login was not accepted - check user and pass (two-step verification is not supported)"
+                    .to_string(),
+            ));
+        }
+        Ok(cookies.join("; "))
+    }
+
+    async fn session(&self, client: &reqwest::Client) -> Result<String, UpdateOutcome> {
+        let mut session = self.session.lock().await;
+        if let Some(cookies) = session.as_ref() {
+            return Ok(cookies.clone());
+        }
+        let cookies = self.login(client).await?;
+        *session = Some(cookies.clone());
+        Ok(cookies)
+    }
+
+    // Sends a request with the session, logging in again once if it expired
+    async fn call(
+        &self,
+        client: &reqwest::Client,
+        method: Method,
+        path: &str,
+        form: &[(&str, &str)],
+    ) -> Result<Value, UpdateOutcome> {
+        let mut relogged = false;
+        loop {
+            let cookies = self.session(client).await?;
+            let mut request = client
+                .request(method.clone(), format!("https://{}{}", self.site, path))
+                .header(COOKIE, cookies);
+            if !form.is_empty() {
+                request = request.form(form);
+            }
+            let resp = request
+                .send()
+                .await
+                .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+
+            let status = resp.status();
+            if status == StatusCode::UNAUTHORIZED && !relogged {
+                self.session.lock().await.take();
+                relogged = true;
+                continue;
+            }
+            let body = resp.json::<Value>().await.ok();
+            if !status.is_success() {
+                let message = body
+                    .as_ref()
+                    .and_then(|body| body["error"].as_str().map(str::to_string));
+                return Err(api_error(status, message));
+            }
+            let Some(body) = body else {
+                return Err(UpdateOutcome::Failed("unexpected response".to_string()));
+            };
+            if body["succeeded"] == Value::Bool(false) {
+                return Err(UpdateOutcome::Failed(
+                    body["error"]
+                        .as_str()
+                        .unwrap_or("request failed")
+                        .to_string(),
+                ));
+            }
+            return Ok(body);
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Hover {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let value = ip.to_string();
+
+        let body = match self
+            .call(
+                client,
+                Method::GET,
+                &format!("/api/domains/{}/dns", self.zone),
+                &[],
+            )
+            .await
+        {
+            Ok(body) => body,
+            Err(outcome) => return outcome,
+        };
+        let domains: Domains = match serde_json::from_value(body) {
+            Ok(domains) => domains,
+            Err(e) => return UpdateOutcome::Failed(format!("unexpected response: {}", e)),
+        };
+        let existing = domains
+            .domains
+            .into_iter()
+            .flat_map(|d| d.entries)
+            .find(|e| e.name == self.name && e.record_type == record_type);
+
+        let result = match existing {
+            Some(entry) if entry.content == value => return UpdateOutcome::NoChange,
+            Some(entry) => {
+                self.call(
+                    client,
+                    Method::PUT,
+                    &format!("/api/dns/{}", entry.id),
+                    &[("content", &value)],
+                )
+                .await
+            }
+            None => {
+                self.call(
+                    client,
+                    Method::POST,
+                    &format!("/api/domains/{}/dns", self.zone),
+                    &[
+                        ("name", &self.name),
+                        ("type", record_type),
+                        ("content", &value),
+                    ],
+                )
+                .await
+            }
+        };
+        match result {
+            Ok(_) => UpdateOutcome::Good,
+            Err(outcome) => outcome,
+        }
+    }
+
+    // Expired sessions are already replaced in `call`, so an auth failure
+    // that gets here is a rejected login
+    async fn invalidate(&self) -> bool {
+        self.session.lock().await.take();
+        false
+    }
+}
@@ -0,0 +1,306 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const API: &str = "api.hosting.ionos.com/dns/v1";
+
+// With `dyndns` set, the API key is only used to create a Dynamic DNS update
+// URL, which IONOS then resolves to the address the request comes from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct IonosConfig {
+    #[serde(default)]
+    pub dyndns: bool,
+}
+
+// The token is the API key as `<prefix>.<secret>`. In the default mode the
+// record is patched through the DNS API, with the zone and record IDs cached
+// after the first update; in Dynamic DNS mode the update URL is cached. Either
+// cache is dropped after a failed update.
+struct Ionos {
+    api: String,
+    key: String,
+    hostname: String,
+    zone: String,
+    ttl: Option<u32>,
+    dyndns: bool,
+    zone_id: Mutex<Option<String>>,
+    record_ids: Mutex<HashMap<&'static str, String>>,
+    update_url: Mutex<Option<String>>,
+}
+
+#[derive(Deserialize)]
+struct Zone {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ZoneRecords {
+    #[serde(default)]
+    records: Vec<Record>,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DynDns {
+    update_url: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err(
+            "token (API key as <prefix>.<secret>) is required by the ionos provider".to_string(),
+        );
+    }
+    let (_, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Ionos {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        key: host.token.clone(),
+        hostname: host.hostname.trim_end_matches('.').to_lowercase(),
+        zone,
+        ttl: host.ttl,
+        dyndns: host.ionos.as_ref().is_some_and(|ionos| ionos.dyndns),
+        zone_id: Mutex::new(None),
+        record_ids: Mutex::new(HashMap::new()),
+        update_url: Mutex::new(None),
+    }))
+}
+
+impl Ionos {
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, UpdateOutcome> {
+        let resp = request
+            .header("X-API-Key", &self.key)
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            // Errors come as a list of `{ code, message }`
+            let message = resp.json::<Vec<ApiError>>().await.ok().map(|errors| {
+                errors
+                    .into_iter()
+                    .map(|e| format!("{} {}", e.code, e.message).trim().to_string())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            });
+            return Err(api_error(status, message));
+        }
+
+        resp.json()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(format!("unexpected response: {}", e)))
+    }
+
+    async fn zone_id(&self, client: &reqwest::Client) -> Result<String, UpdateOutcome> {
+        let mut cached = self.zone_id.lock().await;
+        if let Some(id) = cached.as_ref() {
+            return Ok(id.clone());
+        }
+
+        let zones: Vec<Zone> = self
+            .send(client.get(format!("https://{}/zones", self.api)))
+            .await?;
+        let Some(zone) = zones.into_iter().find(|z| z.name == self.zone) else {
+            return Err(UpdateOutcome::Failed(format!(
+                "zone '{}' not found in this account",
+                self.zone
+            )));
+        };
+
+        *cached = Some(zone.id.clone());
+        Ok(zone.id)
+    }
+
+    // Returns the cached record ID, or looks the record up. `Ok(None)` means
+    // there is no record of this type yet.
+    async fn find_record(
+        &self,
+        client: &reqwest::Client,
+        zone_id: &str,
+        record_type: &'static str,
+        ip: IpAddr,
+    ) -> Result<Option<String>, UpdateOutcome> {
+        if let Some(id) = self.record_ids.lock().await.get(record_type) {
+            return Ok(Some(id.clone()));
+        }
+
+        let zone: ZoneRecords = self
+            .send(
+                client
+                    .get(format!("https://{}/zones/{}", self.api, zone_id))
+                    .query(&[
+                        ("suffix", self.hostname.as_str()),
+                        ("recordType", record_type),
+                    ]),
+            )
+            .await?;
+        let Some(record) = zone
+            .records
+            .into_iter()
+            .find(|r| r.name == self.hostname && r.record_type == record_type)
+        else {
+            return Ok(None);
+        };
+
+        self.record_ids
+            .lock()
+            .await
+            .insert(record_type, record.id.clone());
+        if record.content == ip.to_string() {
+            return Err(UpdateOutcome::NoChange);
+        }
+        Ok(Some(record.id))
+    }
+
+    async fn update_record(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+
+        let zone_id = match self.zone_id(client).await {
+            Ok(id) => id,
+            Err(outcome) => return outcome,
+        };
+        let record_id = match self.find_record(client, &zone_id, record_type, ip).await {
+            Ok(id) => id,
+            Err(outcome) => return outcome,
+        };
+
+        let mut record = json!({ "content": ip.to_string() });
+        if let Some(ttl) = self.ttl {
+            record["ttl"] = json!(ttl);
+        }
+        let result = match record_id {
+            Some(id) => self
+                .send::<Record>(
+                    client
+                        .put(format!(
+                            "https://{}/zones/{}/records/{}",
+                            self.api, zone_id, id
+                        ))
+                        .json(&record),
+                )
+                .await
+                .map(|record| record.id),
+            None => {
+                record["name"] = json!(self.hostname);
+                record["type"] = json!(record_type);
+                self.send::<Vec<Record>>(
+                    client
+                        .post(format!("https://{}/zones/{}/records", self.api, zone_id))
+                        .json(&[record]),
+                )
+                .await
+                .and_then(|records| {
+                    records.into_iter().next().map(|r| r.id).ok_or_else(|| {
+                        UpdateOutcome::Failed("no record in the response".to_string())
+                    })
+                })
+            }
+        };
+
+        match result {
+            Ok(id) => {
+                self.record_ids.lock().await.insert(record_type, id);
+                UpdateOutcome::Good
+            }
+            Err(outcome) => {
+                if matches!(outcome, UpdateOutcome::Failed(_)) {
+                    self.record_ids.lock().await.remove(record_type);
+                    self.zone_id.lock().await.take();
+                }
+                outcome
+            }
+        }
+    }
+
+    async fn update_url(&self, client: &reqwest::Client) -> Result<String, UpdateOutcome> {
+        let mut cached = self.update_url.lock().await;
+        if let Some(url) = cached.as_ref() {
+            return Ok(url.clone());
+        }
+
+        let dyndns: DynDns = self
+            .send(
+                client
+                    .post(format!("https://{}/dyndns", self.api))
+                    .json(&json!({
+                        "domains": [self.hostname],
+                        "description": "ddns-updater",
+                    })),
+            )
+            .await?;
+
+        *cached = Some(dyndns.update_url.clone());
+        Ok(dyndns.update_url)
+    }
+
+    async fn update_dyndns(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let url = match self.update_url(client).await {
+            Ok(url) => url,
+            Err(outcome) => return outcome,
+        };
+        // The update URL is issued for the IPv4 host; its IPv6 twin sets the
+        // AAAA record instead
+        let url = if ip.is_ipv6() {
+            url.replacen("://ipv4.", "://ipv6.", 1)
+        } else {
+            url
+        };
+
+        let resp = match client.get(&url).send().await {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+        let status = resp.status();
+        if status.is_success() {
+            return UpdateOutcome::Good;
+        }
+
+        // The URL may have been revoked; create a new one next time
+        self.update_url.lock().await.take();
+        api_error(status, None)
+    }
+}
+
+#[async_trait]
+impl Provider for Ionos {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        if self.dyndns {
+            self.update_dyndns(client, ip).await
+        } else {
+            self.update_record(client, ip).await
+        }
+    }
+}
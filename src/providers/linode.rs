@@ -0,0 +1,220 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const API: &str = "api.linode.com/v4";
+
+// The domain and record IDs are resolved on the first update and cached, so
+// later updates are a single PUT. A failed update drops the cached record ID,
+// so a record deleted in the meantime is resolved (or created) again.
+struct Linode {
+    api: String,
+    token: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+    domain_id: Mutex<Option<u64>>,
+    record_ids: Mutex<HashMap<&'static str, u64>>,
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    data: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct Domain {
+    id: u64,
+    domain: String,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    id: u64,
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    target: String,
+}
+
+#[derive(Deserialize)]
+struct Errors {
+    errors: Vec<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    reason: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err("token (personal access token) is required by the linode provider".to_string());
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Linode {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        token: host.token.clone(),
+        zone,
+        name,
+        ttl: host.ttl,
+        domain_id: Mutex::new(None),
+        record_ids: Mutex::new(HashMap::new()),
+    }))
+}
+
+impl Linode {
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, UpdateOutcome> {
+        let resp = request
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let message = resp.json::<Errors>().await.ok().map(|e| {
+                e.errors
+                    .into_iter()
+                    .map(|e| e.reason)
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            });
+            return Err(api_error(status, message));
+        }
+
+        resp.json()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(format!("unexpected response: {}", e)))
+    }
+
+    async fn domain_id(&self, client: &reqwest::Client) -> Result<u64, UpdateOutcome> {
+        let mut cached = self.domain_id.lock().await;
+        if let Some(id) = *cached {
+            return Ok(id);
+        }
+
+        let domains: Page<Domain> = self
+            .send(
+                client
+                    .get(format!("https://{}/domains", self.api))
+                    .header("X-Filter", json!({ "domain": self.zone }).to_string()),
+            )
+            .await?;
+        let Some(domain) = domains.data.into_iter().find(|d| d.domain == self.zone) else {
+            return Err(UpdateOutcome::Failed(format!(
+                "domain '{}' not found in this account",
+                self.zone
+            )));
+        };
+
+        *cached = Some(domain.id);
+        Ok(domain.id)
+    }
+
+    // Returns the record ID with the record's current target, or the cached ID
+    // without one: a host is only updated once its address changed or the
+    // record drifted, so a cached record is written without checking it first.
+    // `Ok(None)` means there is no record of this type yet.
+    async fn find_record(
+        &self,
+        client: &reqwest::Client,
+        domain_id: u64,
+        record_type: &'static str,
+    ) -> Result<Option<(u64, Option<String>)>, UpdateOutcome> {
+        if let Some(id) = self.record_ids.lock().await.get(record_type) {
+            return Ok(Some((*id, None)));
+        }
+
+        let records: Page<Record> = self
+            .send(
+                client
+                    .get(format!(
+                        "https://{}/domains/{}/records",
+                        self.api, domain_id
+                    ))
+                    .query(&[("page_size", "500")])
+                    .header(
+                        "X-Filter",
+                        json!({ "name": self.name, "type": record_type }).to_string(),
+                    ),
+            )
+            .await?;
+        let Some(record) = records
+            .data
+            .into_iter()
+            .find(|r| r.name == self.name && r.record_type == record_type)
+        else {
+            return Ok(None);
+        };
+
+        self.record_ids.lock().await.insert(record_type, record.id);
+        Ok(Some((record.id, Some(record.target))))
+    }
+}
+
+#[async_trait]
+impl Provider for Linode {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+
+        let domain_id = match self.domain_id(client).await {
+            Ok(id) => id,
+            Err(outcome) => return outcome,
+        };
+        let record_id = match self.find_record(client, domain_id, record_type).await {
+            Ok(Some((_, Some(target)))) if target == ip.to_string() => {
+                return UpdateOutcome::NoChange
+            }
+            Ok(record) => record.map(|(id, _)| id),
+            Err(outcome) => return outcome,
+        };
+
+        let mut body = json!({ "target": ip.to_string() });
+        if let Some(ttl) = self.ttl {
+            body["ttl_sec"] = json!(ttl);
+        }
+
+        let request = match record_id {
+            Some(id) => client.put(format!(
+                "https://{}/domains/{}/records/{}",
+                self.api, domain_id, id
+            )),
+            None => {
+                body["type"] = json!(record_type);
+                body["name"] = json!(self.name);
+                client.post(format!(
+                    "https://{}/domains/{}/records",
+                    self.api, domain_id
+                ))
+            }
+        };
+        match self.send::<Record>(request.json(&body)).await {
+            Ok(record) => {
+                self.record_ids.lock().await.insert(record_type, record.id);
+                UpdateOutcome::Good
+            }
+            Err(outcome) => {
+                if matches!(outcome, UpdateOutcome::Failed(_)) {
+                    self.record_ids.lock().await.remove(record_type);
+                }
+                outcome
+            }
+        }
+    }
+}
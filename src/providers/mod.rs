@@ -0,0 +1,350 @@
+// Shared helpers are left unused when only some providers are built
+#![cfg_attr(not(feature = "providers"), allow(dead_code))]
+
+#[cfg(feature = "provider-aliyun")]
+mod aliyun;
+#[cfg(feature = "provider-allinkl")]
+mod allinkl;
+#[cfg(feature = "provider-azure")]
+mod azure;
+#[cfg(feature = "provider-custom")]
+mod custom;
+#[cfg(feature = "provider-desec")]
+mod desec;
+#[cfg(feature = "provider-dnspod")]
+mod dnspod;
+#[cfg(feature = "provider-dreamhost")]
+mod dreamhost;
+#[cfg(feature = "provider-duckdns")]
+mod duckdns;
+mod dyndns2;
+#[cfg(feature = "provider-dynv6")]
+mod dynv6;
+#[cfg(feature = "provider-freedns")]
+mod freedns;
+#[cfg(feature = "provider-gandi")]
+mod gandi;
+#[cfg(feature = "provider-godaddy")]
+mod godaddy;
+#[cfg(feature = "provider-hetzner")]
+mod hetzner;
+#[cfg(feature = "provider-hover")]
+mod hover;
+#[cfg(feature = "provider-ionos")]
+mod ionos;
+#[cfg(feature = "provider-linode")]
+mod linode;
+#[cfg(feature = "provider-namecom")]
+mod namecom;
+#[cfg(feature = "provider-netcup")]
+mod netcup;
+#[cfg(feature = "provider-nextdns")]
+mod nextdns;
+#[cfg(feature = "provider-njalla")]
+mod njalla;
+#[cfg(feature = "provider-oci")]
+mod oci;
+#[cfg(feature = "provider-porkbun")]
+mod porkbun;
+#[cfg(feature = "provider-rfc2136")]
+mod rfc2136;
+#[cfg(feature = "provider-route53")]
+mod route53;
+#[cfg(feature = "provider-servercow")]
+mod servercow;
+#[cfg(feature = "provider-vultr")]
+mod vultr;
+#[cfg(feature = "provider-zoneedit")]
+mod zoneedit;
+
+#[cfg(feature = "provider-azure")]
+pub use azure::AzureConfig;
+#[cfg(feature = "provider-custom")]
+pub use custom::CustomConfig;
+pub use dyndns2::preset_min_interval;
+#[cfg(feature = "provider-dynv6")]
+pub use dynv6::Dynv6Config;
+#[cfg(feature = "provider-ionos")]
+pub use ionos::IonosConfig;
+#[cfg(feature = "provider-njalla")]
+pub use njalla::NjallaConfig;
+#[cfg(feature = "provider-oci")]
+pub use oci::OciConfig;
+#[cfg(feature = "provider-rfc2136")]
+pub use rfc2136::Rfc2136Config;
+#[cfg(feature = "provider-route53")]
+pub use route53::Route53Config;
+
+use crate::HostConfig;
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Result of one update request as reported by the provider
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateOutcome {
+    Good,
+    // The record already held the address
+    NoChange,
+    AuthFailed(String),
+    Failed(String),
+    // The provider blocked the host; retrying would only make it worse
+    Blocked(String),
+}
+
+#[async_trait]
+pub trait Provider: Send + Sync {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome;
+
+    // Drops cached credentials such as access tokens after an auth failure.
+    // Returns whether a retry may now succeed.
+    async fn invalidate(&self) -> bool {
+        false
+    }
+
+    // How long an unchanged record may go without an update before it is
+    // pushed again anyway
+    fn refresh_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    fn supports_ipv6(&self) -> bool {
+        true
+    }
+}
+
+type Builder = fn(&HostConfig) -> Result<Arc<dyn Provider>, String>;
+
+// Providers by the name used in the `provider` field of a host
+const REGISTRY: &[(&str, Builder)] = &[
+    ("dyndns2", dyndns2::dyndns2),
+    ("google", dyndns2::google),
+    ("dyfi", dyndns2::dyfi),
+    ("noip", dyndns2::noip),
+    ("ovh", dyndns2::ovh),
+    ("he", dyndns2::he),
+    ("strato", dyndns2::strato),
+    ("inwx", dyndns2::inwx),
+    ("dnsomatic", dyndns2::dnsomatic),
+    ("loopia", dyndns2::loopia),
+    ("easydns", dyndns2::easydns),
+    ("spdyn", dyndns2::spdyn),
+    ("dnshome", dyndns2::dnshome),
+    ("dode", dyndns2::dode),
+    ("variomedia", dyndns2::variomedia),
+    #[cfg(feature = "provider-duckdns")]
+    ("duckdns", duckdns::build),
+    #[cfg(feature = "provider-desec")]
+    ("desec", desec::build),
+    #[cfg(feature = "provider-dynv6")]
+    ("dynv6", dynv6::build),
+    #[cfg(feature = "provider-freedns")]
+    ("freedns", freedns::build),
+    #[cfg(feature = "provider-porkbun")]
+    ("porkbun", porkbun::build),
+    #[cfg(feature = "provider-gandi")]
+    ("gandi", gandi::build),
+    #[cfg(feature = "provider-godaddy")]
+    ("godaddy", godaddy::build),
+    #[cfg(feature = "provider-hetzner")]
+    ("hetzner", hetzner::build),
+    #[cfg(feature = "provider-linode")]
+    ("linode", linode::build),
+    #[cfg(feature = "provider-njalla")]
+    ("njalla", njalla::build),
+    #[cfg(feature = "provider-route53")]
+    ("route53", route53::build),
+    #[cfg(feature = "provider-azure")]
+    ("azure", azure::build),
+    #[cfg(feature = "provider-vultr")]
+    ("vultr", vultr::build),
+    #[cfg(feature = "provider-namecom")]
+    ("namecom", namecom::build),
+    #[cfg(feature = "provider-dreamhost")]
+    ("dreamhost", dreamhost::build),
+    #[cfg(feature = "provider-zoneedit")]
+    ("zoneedit", zoneedit::build),
+    #[cfg(feature = "provider-nextdns")]
+    ("nextdns", nextdns::build),
+    #[cfg(feature = "provider-dnspod")]
+    ("dnspod", dnspod::build),
+    #[cfg(feature = "provider-aliyun")]
+    ("aliyun", aliyun::build),
+    #[cfg(feature = "provider-ionos")]
+    ("ionos", ionos::build),
+    #[cfg(feature = "provider-netcup")]
+    ("netcup", netcup::build),
+    #[cfg(feature = "provider-allinkl")]
+    ("allinkl", allinkl::build),
+    #[cfg(feature = "provider-oci")]
+    ("oci", oci::build),
+    #[cfg(feature = "provider-hover")]
+    ("hover", hover::build),
+    #[cfg(feature = "provider-servercow")]
+    ("servercow", servercow::build),
+    #[cfg(feature = "provider-rfc2136")]
+    ("rfc2136", rfc2136::build),
+    #[cfg(feature = "provider-custom")]
+    ("custom", custom::build),
+];
+
+pub fn default_name() -> String {
+    "dyndns2".to_string()
+}
+
+// Creates the provider for a host, or explains what is missing in its config
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    match REGISTRY.iter().find(|(name, _)| *name == host.provider) {
+        Some((_, builder)) => builder(host),
+        None => Err(format!(
+            "unknown provider '{}' (known: {})",
+            host.provider,
+            REGISTRY
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+// Classifies HTTP-level failures; 401 and 403 mean the credentials were rejected
+pub fn status_outcome(status: reqwest::StatusCode) -> Option<UpdateOutcome> {
+    if status.is_success() {
+        return None;
+    }
+    let message = format!(
+        "status: {} ({})",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("Unknown")
+    );
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        Some(UpdateOutcome::AuthFailed(message))
+    } else {
+        Some(UpdateOutcome::Failed(message))
+    }
+}
+
+// Outcome for a failed API call, with the provider's explanation if it gave one
+pub fn api_error(status: reqwest::StatusCode, message: Option<String>) -> UpdateOutcome {
+    let message = format!(
+        "status: {} ({})",
+        status.as_u16(),
+        message.unwrap_or_else(|| status.canonical_reason().unwrap_or("Unknown").to_string())
+    );
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        UpdateOutcome::AuthFailed(message)
+    } else {
+        UpdateOutcome::Failed(message)
+    }
+}
+
+pub fn request_error(e: reqwest::Error) -> String {
+    if e.is_timeout() {
+        "timeout - check internet connection".to_string()
+    } else if e.is_connect() {
+        "connection failed - check ddns provider".to_string()
+    } else {
+        format!("request error: {}", e)
+    }
+}
+
+// For APIs that sign requests (AWS SigV4 and its look-alikes)
+pub fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    use hmac::{Hmac, Mac};
+    let mut mac =
+        Hmac::<sha2::Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Escapes everything but RFC 3986 unreserved characters
+pub fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// Text of the first element with this name, trimmed and with entities decoded.
+// Attributes are ignored; good enough for the flat answers of SOAP and UPnP.
+pub(crate) fn xml_element(body: &str, name: &str) -> Option<String> {
+    let open = format!("<{}", name);
+    let mut from = 0;
+    let start = loop {
+        let tag = body[from..].find(&open)? + from + open.len();
+        // Skip elements that merely start with the name, like `<ReturnCode>`
+        match body[tag..].chars().next()? {
+            '>' => break tag + 1,
+            c if c.is_whitespace() => break body[tag..].find('>')? + tag + 1,
+            _ => from = tag,
+        }
+    };
+    let end = body[start..].find(&format!("</{}>", name))? + start;
+    Some(xml_unescape(body[start..end].trim()))
+}
+
+pub(crate) fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Splits a hostname into the record name relative to its zone and the zone.
+// Without a configured zone the last two labels are taken as the zone, which
+// is wrong for zones like `example.co.uk` - those need `zone` set.
+pub fn split_hostname(hostname: &str, zone: &str) -> Result<(String, String), String> {
+    let hostname = hostname.trim_end_matches('.').to_lowercase();
+    if hostname.is_empty() {
+        return Err("hostname is missing".to_string());
+    }
+
+    let zone = if zone.is_empty() {
+        let labels: Vec<&str> = hostname.split('.').collect();
+        if labels.len() < 2 {
+            return Err(format!("hostname '{}' has no zone", hostname));
+        }
+        labels[labels.len() - 2..].join(".")
+    } else {
+        zone.trim_end_matches('.').to_lowercase()
+    };
+
+    if hostname == zone {
+        return Ok((String::new(), zone));
+    }
+    match hostname.strip_suffix(&format!(".{}", zone)) {
+        Some(name) => Ok((name.to_string(), zone)),
+        None => Err(format!("hostname '{}' is not in zone '{}'", hostname, zone)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml_element_reads_text() {
+        let body = concat!(
+            r#"<Envelope><Body><ErrorResponse><ReturnCode>1</ReturnCode>"#,
+            r#"<Return xsi:type="xsd:string"> a &amp; b </Return><Empty/>"#,
+            r#"</ErrorResponse></Body></Envelope>"#
+        );
+        assert_eq!(xml_element(body, "ReturnCode").as_deref(), Some("1"));
+        assert_eq!(xml_element(body, "Return").as_deref(), Some("a & b"));
+        assert_eq!(xml_element(body, "Empty"), None);
+        assert_eq!(xml_element(body, "Message"), None);
+    }
+}
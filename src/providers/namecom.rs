@@ -0,0 +1,126 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const API: &str = "api.name.com/v4";
+
+// Name.com has no upsert, so the record is looked up first. Its answer is
+// updated with a PUT, or the record is created if there is none.
+struct NameCom {
+    api: String,
+    user: String,
+    token: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct Records {
+    #[serde(default)]
+    records: Vec<Record>,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    id: u64,
+    #[serde(default)]
+    host: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    answer: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.user.is_empty() || host.token.is_empty() {
+        return Err(
+            "user (username) and token (API token) are required by the namecom provider"
+                .to_string(),
+        );
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(NameCom {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        user: host.user.clone(),
+        token: host.token.clone(),
+        zone,
+        name,
+        ttl: host.ttl,
+    }))
+}
+
+impl NameCom {
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, UpdateOutcome> {
+        let resp = request
+            .basic_auth(&self.user, Some(&self.token))
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let message = resp.json::<Value>().await.ok().and_then(|body| {
+            let message = body["message"].as_str()?;
+            Some(match body["details"].as_str() {
+                Some(details) => format!("{}: {}", message, details),
+                None => message.to_string(),
+            })
+        });
+        Err(api_error(status, message))
+    }
+}
+
+#[async_trait]
+impl Provider for NameCom {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let records_url = format!("https://{}/domains/{}/records", self.api, self.zone);
+
+        let records = match self
+            .send(client.get(&records_url).query(&[("perPage", "1000")]))
+            .await
+        {
+            Ok(resp) => match resp.json::<Records>().await {
+                Ok(records) => records.records,
+                Err(e) => return UpdateOutcome::Failed(format!("unexpected response: {}", e)),
+            },
+            Err(outcome) => return outcome,
+        };
+        let existing = records
+            .into_iter()
+            .find(|r| r.host == self.name && r.record_type == record_type);
+
+        let mut body = json!({
+            "host": self.name,
+            "type": record_type,
+            "answer": ip.to_string(),
+        });
+        if let Some(ttl) = self.ttl {
+            body["ttl"] = json!(ttl);
+        }
+        let request = match existing {
+            Some(record) if record.answer == ip.to_string() => return UpdateOutcome::NoChange,
+            Some(record) => client.put(format!("{}/{}", records_url, record.id)),
+            None => client.post(&records_url),
+        };
+
+        match self.send(request.json(&body)).await {
+            Ok(_) => UpdateOutcome::Good,
+            Err(outcome) => outcome,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use super::{request_error, split_hostname, status_outcome, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use log::warn;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const ENDPOINT: &str = "ccp.netcup.net/run/webservice/servers/endpoint.php?JSON";
+
+// The CCP API is session based: every update logs in, reads the zone's records,
+// writes the changed one and logs out again. Netcup sessions expire after a
+// few minutes anyway, so there is nothing worth keeping between updates.
+struct Netcup {
+    endpoint: String,
+    customer_number: String,
+    api_key: String,
+    api_password: String,
+    zone: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    status: String,
+    statuscode: u32,
+    #[serde(default)]
+    shortmessage: String,
+    #[serde(default)]
+    longmessage: String,
+    #[serde(default)]
+    responsedata: Value,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    id: String,
+    hostname: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    destination: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.user.is_empty() {
+        return Err("user (customer number) is required by the netcup provider".to_string());
+    }
+    if host.token.is_empty() || host.pass.is_empty() {
+        return Err(
+            "token (API key) and pass (API password) are required by the netcup provider"
+                .to_string(),
+        );
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Netcup {
+        endpoint: if host.ddns.is_empty() {
+            ENDPOINT.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        customer_number: host.user.clone(),
+        api_key: host.token.clone(),
+        api_password: host.pass.clone(),
+        zone,
+        // The zone apex is `@` at Netcup
+        name: if name.is_empty() {
+            "@".to_string()
+        } else {
+            name
+        },
+    }))
+}
+
+impl Netcup {
+    // Sends one action and returns its `responsedata`
+    async fn call(
+        &self,
+        client: &reqwest::Client,
+        action: &str,
+        mut param: Value,
+    ) -> Result<Value, UpdateOutcome> {
+        param["customernumber"] = json!(self.customer_number);
+        param["apikey"] = json!(self.api_key);
+
+        let resp = client
+            .post(format!("https://{}", self.endpoint))
+            .json(&json!({ "action": action, "param": param }))
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return Err(outcome);
+        }
+        let body: Response = resp
+            .json()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(format!("unexpected response: {}", e)))?;
+
+        if body.status == "success" {
+            return Ok(body.responsedata);
+        }
+        let message = format!(
+            "{} {} ({})",
+            body.statuscode, body.shortmessage, body.longmessage
+        );
+        // A rejected login means bad credentials; everything else is a failure
+        // of the request itself
+        if action == "login" {
+            Err(UpdateOutcome::AuthFailed(message))
+        } else {
+            Err(UpdateOutcome::Failed(message))
+        }
+    }
+
+    async fn update_records(
+        &self,
+        client: &reqwest::Client,
+        session_id: &str,
+        ip: IpAddr,
+    ) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let session = json!({ "domainname": self.zone, "apisessionid": session_id });
+
+        let data = match self.call(client, "infoDnsRecords", session.clone()).await {
+            Ok(data) => data,
+            Err(outcome) => return outcome,
+        };
+        let records: Vec<Record> =
+            serde_json::from_value(data["dnsrecords"].clone()).unwrap_or_default();
+        let existing = records
+            .into_iter()
+            .find(|r| r.hostname == self.name && r.record_type == record_type);
+        if existing
+            .as_ref()
+            .is_some_and(|r| r.destination == ip.to_string())
+        {
+            return UpdateOutcome::NoChange;
+        }
+
+        // Records without an ID are created
+        let mut record = json!({
+            "hostname": self.name,
+            "type": record_type,
+            "destination": ip.to_string(),
+            "deleterecord": false,
+        });
+        if let Some(existing) = existing {
+            record["id"] = json!(existing.id);
+        }
+        let mut param = session;
+        param["dnsrecordset"] = json!({ "dnsrecords": [record] });
+
+        match self.call(client, "updateDnsRecords", param).await {
+            Ok(_) => UpdateOutcome::Good,
+            Err(outcome) => outcome,
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Netcup {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let login = self
+            .call(client, "login", json!({ "apipassword": self.api_password }))
+            .await;
+        let session_id = match login {
+            Ok(data) => match data["apisessionid"].as_str() {
+                Some(id) => id.to_string(),
+                None => return UpdateOutcome::Failed("login returned no session".to_string()),
+            },
+            Err(outcome) => return outcome,
+        };
+
+        let outcome = self.update_records(client, &session_id, ip).await;
+
+        // A failed logout only leaves a session behind until it expires
+        if let Err(UpdateOutcome::Failed(e) | UpdateOutcome::AuthFailed(e)) = self
+            .call(client, "logout", json!({ "apisessionid": session_id }))
+            .await
+        {
+            warn!("⚠ Netcup logout failed: {}", e);
+        }
+        outcome
+    }
+}
@@ -0,0 +1,74 @@
+use super::{request_error, status_outcome, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const ENDPOINT: &str = "link-ip.nextdns.io";
+
+// NextDNS links the address the request comes from, so the detected address
+// is only used to decide when to call the link. The response body is the
+// address that was linked, which is checked against the detected one.
+struct NextDns {
+    endpoint: String,
+    link: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    // Accepts the whole link-ip URL as shown by NextDNS, or just its path
+    let link = host
+        .token
+        .trim_start_matches("https://")
+        .trim_start_matches(ENDPOINT)
+        .trim_matches('/');
+    if link.split('/').filter(|part| !part.is_empty()).count() != 2 {
+        return Err(
+            "token (the profile's link-ip URL or its <profile>/<key> path) is required by the nextdns provider"
+                .to_string(),
+        );
+    }
+
+    Ok(Arc::new(NextDns {
+        endpoint: if host.ddns.is_empty() {
+            ENDPOINT.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        link: link.to_string(),
+    }))
+}
+
+#[async_trait]
+impl Provider for NextDns {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let resp = match client
+            .get(format!("https://{}/{}", self.endpoint, self.link))
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return outcome;
+        }
+
+        let body = match resp.text().await {
+            Ok(body) => body,
+            Err(e) => return UpdateOutcome::Failed(format!("failed to read response: {}", e)),
+        };
+        match body.trim().parse::<IpAddr>() {
+            Ok(linked) if linked == ip => UpdateOutcome::Good,
+            Ok(linked) => UpdateOutcome::Failed(format!(
+                "NextDNS linked {} instead of {} - the request left over another connection",
+                linked, ip
+            )),
+            Err(_) => UpdateOutcome::Failed(format!("unexpected response: {}", body.trim())),
+        }
+    }
+
+    // The linked IP is IPv4 only
+    fn supports_ipv6(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,94 @@
+use super::{api_error, request_error, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const ENDPOINT: &str = "njal.la/update/";
+
+// With `auto` set, Njalla takes the address from the request's source instead
+// of the detected one; that only works for the family the request goes out over.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct NjallaConfig {
+    #[serde(default)]
+    pub auto: bool,
+}
+
+// Dynamic records in Njalla each have their own key. The result is reported
+// in a JSON body whose `status` mirrors an HTTP status code.
+struct Njalla {
+    endpoint: String,
+    hostname: String,
+    key: String,
+    auto: bool,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    status: u16,
+    #[serde(default)]
+    message: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err(
+            "token (the record's dynamic key) is required by the njalla provider".to_string(),
+        );
+    }
+    if host.hostname.is_empty() {
+        return Err("hostname is required by the njalla provider".to_string());
+    }
+
+    Ok(Arc::new(Njalla {
+        endpoint: if host.ddns.is_empty() {
+            ENDPOINT.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        hostname: host.hostname.clone(),
+        key: host.token.clone(),
+        auto: host.njalla.as_ref().is_some_and(|njalla| njalla.auto),
+    }))
+}
+
+#[async_trait]
+impl Provider for Njalla {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let mut query = vec![("h", self.hostname.clone()), ("k", self.key.clone())];
+        if self.auto {
+            query.push(("auto", String::new()));
+        } else {
+            let param = if ip.is_ipv4() { "a" } else { "aaaa" };
+            query.push((param, ip.to_string()));
+        }
+
+        let resp = match client
+            .get(format!("https://{}", self.endpoint))
+            .query(&query)
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+
+        let http_status = resp.status();
+        let body = match resp.json::<Response>().await {
+            Ok(body) => body,
+            Err(e) if http_status.is_success() => {
+                return UpdateOutcome::Failed(format!("unexpected response: {}", e))
+            }
+            Err(_) => return api_error(http_status, None),
+        };
+
+        let message = format!("status: {} ({})", body.status, body.message);
+        match body.status {
+            200..=299 if body.message.contains("unchanged") => UpdateOutcome::NoChange,
+            200..=299 => UpdateOutcome::Good,
+            401 | 403 => UpdateOutcome::AuthFailed(message),
+            _ => UpdateOutcome::Failed(message),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use super::{request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const API: &str = "api.porkbun.com/api/json/v3";
+
+// Porkbun's JSON API authenticates every call with the API key pair in the
+// request body and reports errors as `{"status": "ERROR", "message": ...}`.
+struct Porkbun {
+    api: String,
+    apikey: String,
+    secretapikey: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ApiResponse {
+    status: String,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    records: Vec<Record>,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    id: String,
+    content: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() || host.secret.is_empty() {
+        return Err(
+            "porkbun needs the API key in token and the secret API key in secret".to_string(),
+        );
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Porkbun {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        apikey: host.token.clone(),
+        secretapikey: host.secret.clone(),
+        zone,
+        name,
+        ttl: host.ttl,
+    }))
+}
+
+impl Porkbun {
+    async fn call(
+        &self,
+        client: &reqwest::Client,
+        path: &str,
+        mut body: Value,
+    ) -> Result<ApiResponse, UpdateOutcome> {
+        body["apikey"] = json!(self.apikey);
+        body["secretapikey"] = json!(self.secretapikey);
+
+        let resp = client
+            .post(format!("https://{}/{}", self.api, path))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+        let status = resp.status();
+
+        // Errors come with a 4xx status and a JSON body explaining them
+        let response: ApiResponse = resp.json().await.map_err(|e| {
+            UpdateOutcome::Failed(format!("unexpected response (status {}): {}", status, e))
+        })?;
+        if response.status == "SUCCESS" {
+            return Ok(response);
+        }
+
+        let message = response
+            .message
+            .unwrap_or_else(|| format!("status {}", status));
+        if message.contains("API key") || status == reqwest::StatusCode::FORBIDDEN {
+            Err(UpdateOutcome::AuthFailed(message))
+        } else {
+            Err(UpdateOutcome::Failed(message))
+        }
+    }
+
+    fn record(&self, record_type: &str, ip: IpAddr) -> Value {
+        let mut record = json!({
+            "name": self.name,
+            "type": record_type,
+            "content": ip.to_string(),
+        });
+        if let Some(ttl) = self.ttl {
+            record["ttl"] = json!(ttl.to_string());
+        }
+        record
+    }
+}
+
+#[async_trait]
+impl Provider for Porkbun {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+
+        let existing = match self
+            .call(
+                client,
+                &format!(
+                    "dns/retrieveByNameType/{}/{}/{}",
+                    self.zone, record_type, self.name
+                ),
+                json!({}),
+            )
+            .await
+        {
+            Ok(response) => response.records,
+            Err(outcome) => return outcome,
+        };
+
+        if existing.iter().any(|r| r.content == ip.to_string()) {
+            return UpdateOutcome::NoChange;
+        }
+
+        let path = match existing.first() {
+            Some(record) => format!("dns/edit/{}/{}", self.zone, record.id),
+            None => format!("dns/create/{}", self.zone),
+        };
+        match self.call(client, &path, self.record(record_type, ip)).await {
+            Ok(_) => UpdateOutcome::Good,
+            Err(outcome) => outcome,
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use super::{split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Sha256, Sha384, Sha512};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+
+const DEFAULT_TTL: u32 = 300;
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Allowed clock difference between us and the server for the TSIG signature
+const FUDGE: u16 = 300;
+
+const OPCODE_UPDATE: u16 = 5 << 11;
+const CLASS_IN: u16 = 1;
+const CLASS_ANY: u16 = 255;
+const TYPE_SOA: u16 = 6;
+const TYPE_TSIG: u16 = 250;
+
+// Where to send the updates and the TSIG key to sign them with. The key's
+// base64 secret is the host's `secret`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Rfc2136Config {
+    pub server: String,
+    pub key_name: String,
+    #[serde(default = "default_algorithm")]
+    pub algorithm: String,
+}
+
+fn default_algorithm() -> String {
+    "hmac-sha256".to_string()
+}
+
+#[derive(Clone, Copy)]
+enum Algorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim_end_matches('.').to_lowercase().as_str() {
+            "hmac-sha256" => Some(Algorithm::Sha256),
+            "hmac-sha384" => Some(Algorithm::Sha384),
+            "hmac-sha512" => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "hmac-sha256",
+            Algorithm::Sha384 => "hmac-sha384",
+            Algorithm::Sha512 => "hmac-sha512",
+        }
+    }
+
+    fn sign(&self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        fn mac<M: Mac + hmac::digest::KeyInit>(key: &[u8], data: &[u8]) -> Vec<u8> {
+            let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        }
+        match self {
+            Algorithm::Sha256 => mac::<Hmac<Sha256>>(key, data),
+            Algorithm::Sha384 => mac::<Hmac<Sha384>>(key, data),
+            Algorithm::Sha512 => mac::<Hmac<Sha512>>(key, data),
+        }
+    }
+}
+
+// Sends an RFC 2136 UPDATE straight to the zone's primary server, replacing
+// the A/AAAA RRset of `hostname`, signed with a TSIG key (RFC 8945). The
+// server's answer is only checked for its response code.
+struct Rfc2136 {
+    server: String,
+    key_name: String,
+    algorithm: Algorithm,
+    secret: Vec<u8>,
+    zone: String,
+    hostname: String,
+    ttl: u32,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    let Some(config) = &host.rfc2136 else {
+        return Err("rfc2136 (server, key_name) is required by the rfc2136 provider".to_string());
+    };
+    if config.server.is_empty() || config.key_name.is_empty() {
+        return Err("rfc2136 needs server and key_name".to_string());
+    }
+    let Some(algorithm) = Algorithm::parse(&config.algorithm) else {
+        return Err(format!(
+            "unsupported TSIG algorithm '{}' (supported: hmac-sha256, hmac-sha384, hmac-sha512)",
+            config.algorithm
+        ));
+    };
+    if host.secret.is_empty() {
+        return Err("secret (base64 TSIG secret) is required by the rfc2136 provider".to_string());
+    }
+    let secret = base64::engine::general_purpose::STANDARD
+        .decode(host.secret.trim())
+        .map_err(|e| format!("secret is not valid base64: {}", e))?;
+    let (_, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Rfc2136 {
+        server: config.server.clone(),
+        key_name: config.key_name.clone(),
+        algorithm,
+        secret,
+        zone,
+        hostname: host.hostname.clone(),
+        ttl: host.ttl.unwrap_or(DEFAULT_TTL),
+    }))
+}
+
+impl Rfc2136 {
+    // Accepts `host`, `host:port`, `ip` and `[ipv6]:port`
+    async fn server_addr(&self) -> Result<SocketAddr, String> {
+        if let Ok(ip) = self.server.parse::<IpAddr>() {
+            return Ok(SocketAddr::new(ip, 53));
+        }
+        let server = if self
+            .server
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+        {
+            self.server.clone()
+        } else {
+            format!("{}:53", self.server)
+        };
+        let mut addrs = tokio::net::lookup_host(server)
+            .await
+            .map_err(|e| format!("cannot resolve server '{}': {}", self.server, e))?;
+        addrs
+            .next()
+            .ok_or_else(|| format!("cannot resolve server '{}'", self.server))
+    }
+
+    fn message(&self, id: u16, ip: IpAddr, now: u64) -> Vec<u8> {
+        let (record_type, rdata) = match ip {
+            IpAddr::V4(v4) => (1u16, v4.octets().to_vec()),
+            IpAddr::V6(v6) => (28u16, v6.octets().to_vec()),
+        };
+
+        let mut msg = Vec::with_capacity(512);
+        msg.extend_from_slice(&id.to_be_bytes());
+        msg.extend_from_slice(&OPCODE_UPDATE.to_be_bytes());
+        // One zone, no prerequisites, two updates, no additional records yet
+        for count in [1u16, 0, 2, 0] {
+            msg.extend_from_slice(&count.to_be_bytes());
+        }
+
+        // Zone section
+        write_name(&mut msg, &self.zone);
+        msg.extend_from_slice(&TYPE_SOA.to_be_bytes());
+        msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+        // Delete the RRset of this type, then add the new address
+        write_name(&mut msg, &self.hostname);
+        msg.extend_from_slice(&record_type.to_be_bytes());
+        msg.extend_from_slice(&CLASS_ANY.to_be_bytes());
+        msg.extend_from_slice(&0u32.to_be_bytes());
+        msg.extend_from_slice(&0u16.to_be_bytes());
+
+        write_name(&mut msg, &self.hostname);
+        msg.extend_from_slice(&record_type.to_be_bytes());
+        msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+        msg.extend_from_slice(&self.ttl.to_be_bytes());
+        msg.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        msg.extend_from_slice(&rdata);
+
+        self.sign(&mut msg, id, now);
+        msg
+    }
+
+    // Appends the TSIG record. The MAC covers the unsigned message followed by
+    // the TSIG variables (RFC 8945, section 4.3.3).
+    fn sign(&self, msg: &mut Vec<u8>, id: u16, now: u64) {
+        let time_signed = &now.to_be_bytes()[2..];
+
+        let mut signed = msg.clone();
+        write_name(&mut signed, &self.key_name.to_lowercase());
+        signed.extend_from_slice(&CLASS_ANY.to_be_bytes());
+        signed.extend_from_slice(&0u32.to_be_bytes());
+        write_name(&mut signed, self.algorithm.name());
+        signed.extend_from_slice(time_signed);
+        signed.extend_from_slice(&FUDGE.to_be_bytes());
+        // Error and other length
+        signed.extend_from_slice(&[0, 0, 0, 0]);
+        let mac = self.algorithm.sign(&self.secret, &signed);
+
+        let mut rdata = Vec::new();
+        write_name(&mut rdata, self.algorithm.name());
+        rdata.extend_from_slice(time_signed);
+        rdata.extend_from_slice(&FUDGE.to_be_bytes());
+        rdata.extend_from_slice(&(mac.len() as u16).to_be_bytes());
+        rdata.extend_from_slice(&mac);
+        rdata.extend_from_slice(&id.to_be_bytes());
+        rdata.extend_from_slice(&[0, 0, 0, 0]);
+
+        write_name(msg, &self.key_name.to_lowercase());
+        msg.extend_from_slice(&TYPE_TSIG.to_be_bytes());
+        msg.extend_from_slice(&CLASS_ANY.to_be_bytes());
+        msg.extend_from_slice(&0u32.to_be_bytes());
+        msg.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        msg.extend_from_slice(&rdata);
+        // ARCOUNT
+        msg[10..12].copy_from_slice(&1u16.to_be_bytes());
+    }
+
+    async fn exchange(&self, msg: &[u8], id: u16) -> Result<u16, String> {
+        let server = self.server_addr().await?;
+        let bind: SocketAddr = if server.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind)
+            .await
+            .map_err(|e| format!("cannot open UDP socket: {}", e))?;
+        socket
+            .connect(server)
+            .await
+            .map_err(|e| format!("cannot reach {}: {}", server, e))?;
+        socket
+            .send(msg)
+            .await
+            .map_err(|e| format!("failed to send update to {}: {}", server, e))?;
+
+        let mut buf = [0u8; 4096];
+        let deadline = tokio::time::Instant::now() + TIMEOUT;
+        loop {
+            let len = tokio::time::timeout_at(deadline, socket.recv(&mut buf))
+                .await
+                .map_err(|_| format!("no answer from {} within {}s", server, TIMEOUT.as_secs()))?
+                .map_err(|e| format!("failed to read answer from {}: {}", server, e))?;
+            // Ignore stray datagrams that are not the answer to this update
+            if len >= 12 && buf[0..2] == id.to_be_bytes() {
+                return Ok(u16::from_be_bytes([buf[2], buf[3]]) & 0x000f);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Rfc2136 {
+    async fn update(&self, _client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        // Not a security measure: TSIG protects the update, the ID only
+        // matches the answer to the request
+        let id = (now.subsec_nanos() ^ (now.as_secs() as u32)) as u16;
+        let msg = self.message(id, ip, now.as_secs());
+
+        match self.exchange(&msg, id).await {
+            Ok(0) => UpdateOutcome::Good,
+            Ok(9) => UpdateOutcome::AuthFailed(
+                "NOTAUTH - the server rejected the TSIG key or is not authoritative for the zone"
+                    .to_string(),
+            ),
+            Ok(5) => UpdateOutcome::AuthFailed(
+                "REFUSED - the key is not allowed to update this zone".to_string(),
+            ),
+            Ok(rcode) => UpdateOutcome::Failed(format!("update failed: {}", rcode_name(rcode))),
+            Err(e) => UpdateOutcome::Failed(e),
+        }
+    }
+}
+
+fn write_name(buf: &mut Vec<u8>, name: &str) {
+    for label in name
+        .trim_end_matches('.')
+        .split('.')
+        .filter(|l| !l.is_empty())
+    {
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+}
+
+fn rcode_name(rcode: u16) -> String {
+    match rcode {
+        1 => "FORMERR - the server could not parse the update".to_string(),
+        2 => "SERVFAIL - server-side error, retrying later".to_string(),
+        3 => "NXDOMAIN - the zone does not exist on this server".to_string(),
+        4 => "NOTIMP - the server does not support dynamic updates".to_string(),
+        6 => "YXDOMAIN".to_string(),
+        7 => "YXRRSET".to_string(),
+        8 => "NXRRSET".to_string(),
+        10 => "NOTZONE - hostname is not within the zone".to_string(),
+        _ => format!("rcode {}", rcode),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::hex;
+
+    #[test]
+    fn algorithms_match_rfc_4231() {
+        // Test case 2 of RFC 4231
+        let cases = [
+            (
+                "hmac-sha256",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                "hmac-sha384",
+                "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649",
+            ),
+            (
+                "hmac-sha512",
+                "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+            ),
+        ];
+        for (name, expected) in cases {
+            let algorithm = Algorithm::parse(name).unwrap();
+            assert_eq!(algorithm.name(), name);
+            assert_eq!(
+                hex(&algorithm.sign(b"Jefe", b"what do ya want for nothing?")),
+                expected
+            );
+        }
+        assert!(Algorithm::parse("hmac-md5").is_none());
+    }
+
+    #[test]
+    fn message_is_signed() {
+        let rfc2136 = Rfc2136 {
+            server: "127.0.0.1".to_string(),
+            key_name: "DDNS-key".to_string(),
+            algorithm: Algorithm::Sha256,
+            secret: b"secret-key-for-tests".to_vec(),
+            zone: "example.com".to_string(),
+            hostname: "www.example.com".to_string(),
+            ttl: 300,
+        };
+        let msg = rfc2136.message(0x1234, IpAddr::from([192, 0, 2, 1]), 1_700_000_000);
+
+        // Built independently from the layout in RFC 2136 and RFC 8945
+        assert_eq!(
+            hex(&msg),
+            concat!(
+                "123428000001000000020001",
+                "076578616d706c6503636f6d0000060001",
+                "03777777076578616d706c6503636f6d00000100ff000000000000",
+                "03777777076578616d706c6503636f6d00000100010000012c0004c0000201",
+                "0864646e732d6b65790000fa00ff00000000003d",
+                "0b686d61632d736861323536000000",
+                "6553f100012c0020",
+                "4ee91c9d35792b7a131a316b489396a49713aff7ef5feb40be6f67379eeb1fb4",
+                "123400000000"
+            )
+        );
+    }
+}
@@ -0,0 +1,548 @@
+use super::{api_error, hex, hmac_sha256, request_error, xml_element, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+// Route53 is a global service, but requests are signed for us-east-1
+const SCOPE: Scope = Scope {
+    host: "route53.amazonaws.com",
+    region: "us-east-1",
+    service: "route53",
+};
+const DEFAULT_TTL: u32 = 300;
+const METADATA: &str = "http://169.254.169.254/latest";
+const CONTAINER_METADATA: &str = "http://169.254.170.2";
+
+// Profile of the shared credentials file to use instead of AWS_PROFILE
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Route53Config {
+    #[serde(default)]
+    pub profile: String,
+}
+
+// Each update is a single UPSERT change batch, so the record is created if it
+// does not exist yet. Credentials are looked up like the AWS SDKs do: keys in
+// the config, the AWS_* environment variables, the shared credentials file,
+// then temporary role credentials from the container (ECS) or the EC2 instance
+// metadata service (IMDSv2).
+struct Route53 {
+    zone_id: String,
+    hostname: String,
+    ttl: u32,
+    static_credentials: Option<Credentials>,
+    role_source: RoleSource,
+    role_credentials: Mutex<Option<Credentials>>,
+}
+
+// Where a request is sent, which is part of its signature
+struct Scope {
+    host: &'static str,
+    region: &'static str,
+    service: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    expiration: Option<DateTime<Utc>>,
+}
+
+// Where temporary credentials come from when there are no keys
+enum RoleSource {
+    // ECS task roles and other container credential endpoints (EKS Pod
+    // Identity); the token file is re-read for every fetch, as it is rotated
+    Container {
+        url: String,
+        token: Option<String>,
+        token_file: Option<String>,
+    },
+    Instance,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RoleCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    token: String,
+    expiration: String,
+}
+
+impl From<RoleCredentials> for Credentials {
+    fn from(credentials: RoleCredentials) -> Self {
+        Credentials {
+            access_key_id: credentials.access_key_id,
+            secret_access_key: credentials.secret_access_key,
+            session_token: Some(credentials.token),
+            // Unparseable expirations mean the credentials are fetched again next time
+            expiration: DateTime::parse_from_rfc3339(&credentials.expiration)
+                .ok()
+                .map(|expiration| expiration.with_timezone(&Utc)),
+        }
+    }
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.zone.is_empty() {
+        return Err("zone (hosted zone ID) is required by the route53 provider".to_string());
+    }
+    if host.hostname.is_empty() {
+        return Err("hostname is required by the route53 provider".to_string());
+    }
+    let static_credentials = match (host.token.is_empty(), host.secret.is_empty()) {
+        (true, true) => match environment_credentials() {
+            Some(credentials) => Some(credentials),
+            None => profile_credentials(host)?,
+        },
+        (false, false) => Some(Credentials {
+            access_key_id: host.token.clone(),
+            secret_access_key: host.secret.clone(),
+            session_token: None,
+            expiration: None,
+        }),
+        _ => {
+            return Err(
+                "token (access key ID) and secret (secret access key) must be set together"
+                    .to_string(),
+            )
+        }
+    };
+
+    Ok(Arc::new(Route53 {
+        zone_id: host.zone.trim_start_matches("/hostedzone/").to_string(),
+        hostname: host.hostname.trim_end_matches('.').to_string(),
+        ttl: host.ttl.unwrap_or(DEFAULT_TTL),
+        static_credentials,
+        role_source: role_source(),
+        role_credentials: Mutex::new(None),
+    }))
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn environment_credentials() -> Option<Credentials> {
+    Some(Credentials {
+        access_key_id: env_var("AWS_ACCESS_KEY_ID")?,
+        secret_access_key: env_var("AWS_SECRET_ACCESS_KEY")?,
+        session_token: env_var("AWS_SESSION_TOKEN"),
+        expiration: None,
+    })
+}
+
+// Keys of the selected profile in the shared credentials file. A missing file
+// or `default` profile just moves on to role credentials, but a profile that
+// was asked for has to exist.
+fn profile_credentials(host: &HostConfig) -> Result<Option<Credentials>, String> {
+    let selected = host
+        .route53
+        .as_ref()
+        .map(|config| config.profile.clone())
+        .filter(|profile| !profile.is_empty())
+        .or_else(|| env_var("AWS_PROFILE"));
+    let profile = selected.as_deref().unwrap_or("default");
+
+    let path = match env_var("AWS_SHARED_CREDENTIALS_FILE") {
+        Some(path) => PathBuf::from(path),
+        None => match env_var("HOME") {
+            Some(home) => PathBuf::from(home).join(".aws").join("credentials"),
+            None => PathBuf::new(),
+        },
+    };
+    let credentials = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| parse_profile(&text, profile));
+    match credentials {
+        None if selected.is_some() => Err(format!(
+            "AWS profile '{}' has no keys in {}",
+            profile,
+            path.display()
+        )),
+        credentials => Ok(credentials),
+    }
+}
+
+// Reads a profile of the INI-style shared credentials file
+fn parse_profile(text: &str, profile: &str) -> Option<Credentials> {
+    let mut in_profile = false;
+    let (mut access_key_id, mut secret_access_key, mut session_token) = (None, None, None);
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_profile = name.trim() == profile;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if !in_profile || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let value = Some(value.trim().to_string());
+        match key.trim() {
+            "aws_access_key_id" => access_key_id = value,
+            "aws_secret_access_key" => secret_access_key = value,
+            "aws_session_token" => session_token = value,
+            _ => {}
+        }
+    }
+    Some(Credentials {
+        access_key_id: access_key_id?,
+        secret_access_key: secret_access_key?,
+        session_token,
+        expiration: None,
+    })
+}
+
+fn role_source() -> RoleSource {
+    let url = env_var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI")
+        .map(|uri| format!("{}{}", CONTAINER_METADATA, uri))
+        .or_else(|| env_var("AWS_CONTAINER_CREDENTIALS_FULL_URI"));
+    match url {
+        Some(url) => RoleSource::Container {
+            url,
+            token: env_var("AWS_CONTAINER_AUTHORIZATION_TOKEN"),
+            token_file: env_var("AWS_CONTAINER_AUTHORIZATION_TOKEN_FILE"),
+        },
+        None => RoleSource::Instance,
+    }
+}
+
+impl Route53 {
+    async fn credentials(&self, client: &reqwest::Client) -> Result<Credentials, String> {
+        if let Some(credentials) = &self.static_credentials {
+            return Ok(credentials.clone());
+        }
+
+        let mut cached = self.role_credentials.lock().await;
+        // Renew a few minutes early so a request is never signed with keys
+        // that expire in flight
+        if let Some(credentials) = cached.as_ref() {
+            match credentials.expiration {
+                Some(expiration) if expiration - Duration::minutes(5) > Utc::now() => {
+                    return Ok(credentials.clone())
+                }
+                _ => {}
+            }
+        }
+
+        let credentials = match &self.role_source {
+            RoleSource::Container {
+                url,
+                token,
+                token_file,
+            } => fetch_container_credentials(client, url, token, token_file).await?,
+            RoleSource::Instance => fetch_role_credentials(client).await?,
+        };
+        *cached = Some(credentials.clone());
+        Ok(credentials)
+    }
+}
+
+async fn fetch_container_credentials(
+    client: &reqwest::Client,
+    url: &str,
+    token: &Option<String>,
+    token_file: &Option<String>,
+) -> Result<Credentials, String> {
+    let token = match token_file {
+        Some(path) => Some(
+            std::fs::read_to_string(path)
+                .map_err(|e| format!("failed to read '{}': {}", path, e))?
+                .trim()
+                .to_string(),
+        ),
+        None => token.clone(),
+    };
+    let mut request = client.get(url);
+    if let Some(token) = token {
+        request = request.header(reqwest::header::AUTHORIZATION, token);
+    }
+    let credentials: RoleCredentials = request
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("failed to fetch container credentials: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("unexpected container credentials response: {}", e))?;
+    Ok(credentials.into())
+}
+
+async fn fetch_role_credentials(client: &reqwest::Client) -> Result<Credentials, String> {
+    let token = client
+        .put(format!("{}/api/token", METADATA))
+        .header("X-aws-ec2-metadata-token-ttl-seconds", "300")
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("no access keys configured and no instance metadata: {}", e))?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let url = format!("{}/meta-data/iam/security-credentials/", METADATA);
+    let role = client
+        .get(&url)
+        .header("X-aws-ec2-metadata-token", &token)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("no instance role attached: {}", e))?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+    let role = role.lines().next().unwrap_or_default().trim();
+    if role.is_empty() {
+        return Err("no instance role attached".to_string());
+    }
+
+    let credentials: RoleCredentials = client
+        .get(format!("{}{}", url, role))
+        .header("X-aws-ec2-metadata-token", &token)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("failed to fetch credentials for role '{}': {}", role, e))?
+        .json()
+        .await
+        .map_err(|e| format!("unexpected instance metadata response: {}", e))?;
+    Ok(credentials.into())
+}
+
+#[async_trait]
+impl Provider for Route53 {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let credentials = match self.credentials(client).await {
+            Ok(credentials) => credentials,
+            Err(e) => return UpdateOutcome::AuthFailed(e),
+        };
+
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<ChangeResourceRecordSetsRequest xmlns="https://route53.amazonaws.com/doc/2013-04-01/">"#,
+                "<ChangeBatch><Comment>ddns-updater</Comment><Changes><Change><Action>UPSERT</Action>",
+                "<ResourceRecordSet><Name>{}.</Name><Type>{}</Type><TTL>{}</TTL>",
+                "<ResourceRecords><ResourceRecord><Value>{}</Value></ResourceRecord></ResourceRecords>",
+                "</ResourceRecordSet></Change></Changes></ChangeBatch>",
+                "</ChangeResourceRecordSetsRequest>"
+            ),
+            self.hostname, record_type, self.ttl, ip
+        );
+        let path = format!("/2013-04-01/hostedzone/{}/rrset", self.zone_id);
+
+        let mut request = client
+            .post(format!("https://{}{}", SCOPE.host, path))
+            .header(reqwest::header::CONTENT_TYPE, "application/xml");
+        for (name, value) in sign(&credentials, &SCOPE, "POST", &path, &body, Utc::now()) {
+            request = request.header(name, value);
+        }
+
+        let resp = match request.body(body).send().await {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+
+        let status = resp.status();
+        if status.is_success() {
+            return UpdateOutcome::Good;
+        }
+        let message = resp
+            .text()
+            .await
+            .ok()
+            .and_then(|body| xml_element(&body, "Message"));
+        api_error(status, message)
+    }
+
+    async fn invalidate(&self) -> bool {
+        if self.static_credentials.is_some() {
+            return false;
+        }
+        self.role_credentials.lock().await.take();
+        true
+    }
+}
+
+// AWS Signature Version 4; returns the headers to add to the request
+fn sign(
+    credentials: &Credentials,
+    scope: &Scope,
+    method: &str,
+    path: &str,
+    body: &str,
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let credential_scope = format!("{}/{}/{}/aws4_request", date, scope.region, scope.service);
+
+    let mut headers = vec![
+        ("host", scope.host.to_string()),
+        ("x-amz-date", timestamp.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        path,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(body.as_bytes()))
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp,
+        credential_scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = [date.as_str(), scope.region, scope.service, "aws4_request"]
+        .iter()
+        .fold(
+            format!("AWS4{}", credentials.secret_access_key).into_bytes(),
+            |key, part| hmac_sha256(&key, part),
+        );
+    let signature = hex(&hmac_sha256(&key, &string_to_sign));
+
+    // `host` is set by reqwest itself
+    headers.retain(|(name, _)| *name != "host");
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, credential_scope, signed_headers, signature
+        ),
+    ));
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Credentials and scope of the AWS Signature Version 4 test suite
+    const TEST_SCOPE: Scope = Scope {
+        host: "example.amazonaws.com",
+        region: "us-east-1",
+        service: "service",
+    };
+
+    fn test_credentials(session_token: Option<&str>) -> Credentials {
+        Credentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: session_token.map(str::to_string),
+            expiration: None,
+        }
+    }
+
+    fn authorization(headers: &[(&str, String)]) -> String {
+        headers
+            .iter()
+            .find(|(name, _)| *name == "authorization")
+            .map(|(_, value)| value.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn sign_matches_the_aws_test_suite() {
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        let cases = [
+            // get-vanilla and post-vanilla
+            (
+                "GET",
+                "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31",
+            ),
+            (
+                "POST",
+                "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b",
+            ),
+        ];
+        for (method, signature) in cases {
+            let headers = sign(&test_credentials(None), &TEST_SCOPE, method, "/", "", now);
+            assert_eq!(
+                authorization(&headers),
+                format!(
+                    "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, Signature={}",
+                    signature
+                )
+            );
+            assert!(headers.contains(&("x-amz-date", "20150830T123600Z".to_string())));
+            assert!(!headers.iter().any(|(name, _)| *name == "host"));
+        }
+    }
+
+    #[test]
+    fn parse_profile_reads_the_selected_section() {
+        let text = concat!(
+            "[default]\n",
+            "aws_access_key_id = AKIDDEFAULT\n",
+            "aws_secret_access_key = default-secret\n",
+            "\n",
+            "# temporary keys\n",
+            "[dns]\n",
+            "aws_access_key_id=AKIDDNS\n",
+            "aws_secret_access_key=dns-secret\n",
+            "aws_session_token = dns-token\n",
+            "; aws_session_token = commented\n",
+            "[incomplete]\n",
+            "aws_access_key_id = AKIDINCOMPLETE\n",
+        );
+        assert_eq!(
+            parse_profile(text, "default").map(|c| (c.access_key_id, c.session_token)),
+            Some(("AKIDDEFAULT".to_string(), None))
+        );
+        assert_eq!(
+            parse_profile(text, "dns"),
+            Some(Credentials {
+                access_key_id: "AKIDDNS".to_string(),
+                secret_access_key: "dns-secret".to_string(),
+                session_token: Some("dns-token".to_string()),
+                expiration: None,
+            })
+        );
+        assert_eq!(parse_profile(text, "incomplete"), None);
+        assert_eq!(parse_profile(text, "missing"), None);
+    }
+
+    #[test]
+    fn sign_includes_the_session_token() {
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        let headers = sign(
+            &test_credentials(Some("token")),
+            &TEST_SCOPE,
+            "POST",
+            "/",
+            "",
+            now,
+        );
+        assert!(headers.contains(&("x-amz-security-token", "token".to_string())));
+        assert!(
+            authorization(&headers).contains("SignedHeaders=host;x-amz-date;x-amz-security-token,")
+        );
+    }
+}
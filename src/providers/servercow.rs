@@ -0,0 +1,83 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const API: &str = "api.servercow.de/dns/v1";
+
+// Servercow's POST replaces the record of the same name and type, or creates
+// it, so there is no need to look up the existing record first.
+struct Servercow {
+    api: String,
+    user: String,
+    pass: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.user.is_empty() || host.pass.is_empty() {
+        return Err(
+            "user and pass (DNS API user from the Servercow panel) are required by the servercow provider"
+                .to_string(),
+        );
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Servercow {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        user: host.user.clone(),
+        pass: host.pass.clone(),
+        zone,
+        // The zone apex is an empty name
+        name,
+        ttl: host.ttl,
+    }))
+}
+
+#[async_trait]
+impl Provider for Servercow {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let mut body = json!({
+            "type": record_type,
+            "name": self.name,
+            "content": ip.to_string(),
+        });
+        if let Some(ttl) = self.ttl {
+            body["ttl"] = json!(ttl);
+        }
+
+        let resp = match client
+            .post(format!("https://{}/domains/{}", self.api, self.zone))
+            .header("X-Auth-Username", &self.user)
+            .header("X-Auth-Password", &self.pass)
+            .json(&body)
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+
+        // Errors come as `{ "error": ... }`, sometimes with status 200
+        let status = resp.status();
+        let error = resp
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body["error"].as_str().map(str::to_string));
+        match error {
+            None if status.is_success() => UpdateOutcome::Good,
+            Some(e) if status.is_success() => UpdateOutcome::Failed(e),
+            message => api_error(status, message),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use super::{api_error, request_error, split_hostname, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const API: &str = "api.vultr.com/v2";
+
+// The record is looked up on every update, then its data is PATCHed, or it is
+// created if there is none.
+struct Vultr {
+    api: String,
+    token: String,
+    zone: String,
+    name: String,
+    ttl: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct Records {
+    records: Vec<Record>,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    id: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    name: String,
+    data: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.token.is_empty() {
+        return Err("token (API key) is required by the vultr provider".to_string());
+    }
+    let (name, zone) = split_hostname(&host.hostname, &host.zone)?;
+
+    Ok(Arc::new(Vultr {
+        api: if host.ddns.is_empty() {
+            API.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        token: host.token.clone(),
+        zone,
+        name,
+        ttl: host.ttl,
+    }))
+}
+
+impl Vultr {
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, UpdateOutcome> {
+        let resp = request
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .map_err(|e| UpdateOutcome::Failed(request_error(e)))?;
+
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let message = resp
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body["error"].as_str().map(str::to_string));
+        Err(api_error(status, message))
+    }
+}
+
+#[async_trait]
+impl Provider for Vultr {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let record_type = if ip.is_ipv4() { "A" } else { "AAAA" };
+        let records_url = format!("https://{}/domains/{}/records", self.api, self.zone);
+
+        let records = match self
+            .send(client.get(&records_url).query(&[("per_page", "500")]))
+            .await
+        {
+            Ok(resp) => match resp.json::<Records>().await {
+                Ok(records) => records.records,
+                Err(e) => return UpdateOutcome::Failed(format!("unexpected response: {}", e)),
+            },
+            Err(outcome) => return outcome,
+        };
+        let existing = records
+            .into_iter()
+            .find(|r| r.name == self.name && r.record_type == record_type);
+
+        let mut body = json!({ "data": ip.to_string() });
+        if let Some(ttl) = self.ttl {
+            body["ttl"] = json!(ttl);
+        }
+        let request = match existing {
+            Some(record) if record.data == ip.to_string() => return UpdateOutcome::NoChange,
+            Some(record) => client.patch(format!("{}/{}", records_url, record.id)),
+            None => {
+                body["type"] = json!(record_type);
+                body["name"] = json!(self.name);
+                client.post(&records_url)
+            }
+        };
+
+        match self.send(request.json(&body)).await {
+            Ok(_) => UpdateOutcome::Good,
+            Err(outcome) => outcome,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use super::{request_error, status_outcome, Provider, UpdateOutcome};
+use crate::HostConfig;
+use async_trait::async_trait;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const ENDPOINT: &str = "dynamic.zoneedit.com/auth/dynamic.html";
+
+// Zoneedit answers with a pseudo-XML tag such as
+// `<SUCCESS CODE="200" TEXT="Update succeeded." ...>` or
+// `<ERROR CODE="702" TEXT="Update failed." ...>`.
+struct Zoneedit {
+    endpoint: String,
+    user: String,
+    token: String,
+    hostname: String,
+}
+
+pub fn build(host: &HostConfig) -> Result<Arc<dyn Provider>, String> {
+    if host.user.is_empty() || host.token.is_empty() {
+        return Err(
+            "user and token (dynamic DNS token) are required by the zoneedit provider".to_string(),
+        );
+    }
+    if host.hostname.is_empty() {
+        return Err("hostname is required by the zoneedit provider".to_string());
+    }
+
+    Ok(Arc::new(Zoneedit {
+        endpoint: if host.ddns.is_empty() {
+            ENDPOINT.to_string()
+        } else {
+            host.ddns.clone()
+        },
+        user: host.user.clone(),
+        token: host.token.clone(),
+        hostname: host.hostname.clone(),
+    }))
+}
+
+#[async_trait]
+impl Provider for Zoneedit {
+    async fn update(&self, client: &reqwest::Client, ip: IpAddr) -> UpdateOutcome {
+        let resp = match client
+            .get(format!("https://{}", self.endpoint))
+            .query(&[("host", self.hostname.as_str()), ("dnsto", &ip.to_string())])
+            .basic_auth(&self.user, Some(&self.token))
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => return UpdateOutcome::Failed(request_error(e)),
+        };
+        if let Some(outcome) = status_outcome(resp.status()) {
+            return outcome;
+        }
+
+        match resp.text().await {
+            Ok(body) => parse_response(&body),
+            Err(e) => UpdateOutcome::Failed(format!("failed to read response: {}", e)),
+        }
+    }
+}
+
+fn parse_response(body: &str) -> UpdateOutcome {
+    let body = body.trim();
+    let code = attribute(body, "CODE").unwrap_or_default();
+    let text = attribute(body, "TEXT").unwrap_or_default();
+    let message = format!("{} - {}", code, text);
+
+    if body.starts_with("<SUCCESS") {
+        return match code {
+            // No records need updating
+            "201" => UpdateOutcome::NoChange,
+            _ => UpdateOutcome::Good,
+        };
+    }
+    if !body.starts_with("<ERROR") {
+        return UpdateOutcome::Failed(format!("unexpected response: {}", body));
+    }
+    match code {
+        // Duplicate update for the same host and IP
+        "707" => UpdateOutcome::NoChange,
+        "708" => UpdateOutcome::AuthFailed(message),
+        _ => UpdateOutcome::Failed(message),
+    }
+}
+
+// Value of `NAME="..."` in the response tag
+fn attribute<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+    let start = body.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let len = body[start..].find('"')?;
+    Some(&body[start..start + len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_response_maps_codes() {
+        let cases = [
+            (
+                r#"<SUCCESS CODE="200" TEXT="Update succeeded." ZONE="example.org" IP="203.0.113.7">"#,
+                UpdateOutcome::Good,
+            ),
+            (
+                r#"<SUCCESS CODE="201" TEXT="no update required" ZONE="example.org">"#,
+                UpdateOutcome::NoChange,
+            ),
+            (
+                r#"<ERROR CODE="707" TEXT="Duplicate updates for the same host/ip, adjust client settings" ZONE="example.org">"#,
+                UpdateOutcome::NoChange,
+            ),
+            (
+                r#"<ERROR CODE="708" TEXT="Failed Login: user" ZONE="example.org">"#,
+                UpdateOutcome::AuthFailed("708 - Failed Login: user".to_string()),
+            ),
+            (
+                r#"<ERROR CODE="702" TEXT="Update failed." ZONE="example.org">"#,
+                UpdateOutcome::Failed("702 - Update failed.".to_string()),
+            ),
+            (
+                "Service unavailable",
+                UpdateOutcome::Failed("unexpected response: Service unavailable".to_string()),
+            ),
+        ];
+        for (body, outcome) in cases {
+            assert_eq!(parse_response(body), outcome, "{}", body);
+        }
+    }
+}
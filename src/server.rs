@@ -0,0 +1,251 @@
+use crate::{publish_ip, AppState, PublishOutcome};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
+
+const MAX_REQUEST_SIZE: usize = 8192;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ListenConfig {
+    #[serde(default = "default_address")]
+    pub address: String,
+    pub user: String,
+    pub pass: String,
+}
+
+fn default_address() -> String {
+    "0.0.0.0:8245".to_string()
+}
+
+impl ListenConfig {
+    pub fn is_valid(&self) -> bool {
+        !self.address.is_empty() && !self.user.is_empty() && !self.pass.is_empty()
+    }
+}
+
+struct Response {
+    status: u16,
+    reason: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn ok(body: String) -> Self {
+        Self {
+            status: 200,
+            reason: "OK",
+            body,
+        }
+    }
+
+    fn error(status: u16, reason: &'static str, body: &str) -> Self {
+        Self {
+            status,
+            reason,
+            body: body.to_string(),
+        }
+    }
+}
+
+// Runs the DynDNS2 listener for the current `listen` config and restarts it
+// whenever that section changes.
+pub async fn run(state: Arc<AppState>) {
+    let mut current: Option<ListenConfig> = None;
+    let mut server: Option<JoinHandle<()>> = None;
+
+    loop {
+        let listen = state
+            .config
+            .read()
+            .await
+            .as_ref()
+            .and_then(|c| c.listen.clone());
+
+        if listen != current {
+            if let Some(handle) = server.take() {
+                handle.abort();
+                info!("DynDNS2 listener stopped");
+            }
+            if let Some(listen) = &listen {
+                server = Some(tokio::spawn(serve(listen.clone(), state.clone())));
+            }
+            current = listen;
+        }
+
+        sleep(Duration::from_secs(5)).await;
+    }
+}
+
+async fn serve(listen: ListenConfig, state: Arc<AppState>) {
+    let listener = loop {
+        match TcpListener::bind(&listen.address).await {
+            Ok(listener) => break listener,
+            Err(e) => {
+                error!(
+                    "✗ Cannot listen on {}: {}. Retrying in 10 seconds...",
+                    listen.address, e
+                );
+                sleep(Duration::from_secs(10)).await;
+            }
+        }
+    };
+    info!("✓ Listening for DynDNS2 updates on {}", listen.address);
+
+    let listen = Arc::new(listen);
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tokio::spawn(handle_connection(
+                    stream,
+                    peer,
+                    listen.clone(),
+                    state.clone(),
+                ));
+            }
+            Err(e) => warn!("Failed to accept connection: {}", e),
+        }
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    listen: Arc<ListenConfig>,
+    state: Arc<AppState>,
+) {
+    let response = match timeout(Duration::from_secs(10), read_request(&mut stream)).await {
+        Ok(Some(request)) => handle_request(&request, peer, &listen, &state).await,
+        Ok(None) => Response::error(400, "Bad Request", "badrequest"),
+        Err(_) => return,
+    };
+
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.reason,
+        response.body.len()
+    );
+    if response.status == 401 {
+        head.push_str("WWW-Authenticate: Basic realm=\"ddns-updater\"\r\n");
+    }
+    head.push_str("\r\n");
+    head.push_str(&response.body);
+
+    stream.write_all(head.as_bytes()).await.ok();
+    stream.shutdown().await.ok();
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        if buf.len() > MAX_REQUEST_SIZE {
+            return None;
+        }
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    String::from_utf8(buf).ok()
+}
+
+async fn handle_request(
+    request: &str,
+    peer: SocketAddr,
+    listen: &ListenConfig,
+    state: &Arc<AppState>,
+) -> Response {
+    let mut lines = request.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Response::error(400, "Bad Request", "badrequest");
+    };
+
+    if method != "GET" {
+        return Response::error(405, "Method Not Allowed", "badrequest");
+    }
+
+    let Ok(url) = reqwest::Url::parse(&format!("http://localhost{}", target)) else {
+        return Response::error(400, "Bad Request", "badrequest");
+    };
+    if url.path() != "/nic/update" {
+        return Response::error(404, "Not Found", "notfound");
+    }
+
+    let authorized = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+        .is_some_and(|(_, value)| check_auth(value.trim(), listen));
+    if !authorized {
+        warn!(
+            "⚠ Rejected DynDNS2 update from {}: bad credentials",
+            peer.ip()
+        );
+        return Response::error(401, "Unauthorized", "badauth");
+    }
+
+    // Without myip the DynDNS2 protocol uses the address the request came from
+    let myip = url
+        .query_pairs()
+        .find(|(key, _)| key == "myip")
+        .map(|(_, value)| value.into_owned());
+    let ip = match myip {
+        Some(value) => match value.parse::<IpAddr>() {
+            Ok(ip) => ip,
+            Err(_) => return Response::error(400, "Bad Request", "badip"),
+        },
+        None => peer.ip(),
+    };
+
+    info!("DynDNS2 update from {}: myip={}", peer.ip(), ip);
+    match publish_ip(state, &ip.to_string()).await {
+        PublishOutcome::Updated => Response::ok(format!("good {}", ip)),
+        PublishOutcome::Unchanged | PublishOutcome::Standby => {
+            Response::ok(format!("nochg {}", ip))
+        }
+        PublishOutcome::Failed | PublishOutcome::AuthFailed => Response::ok("911".to_string()),
+    }
+}
+
+fn check_auth(header: &str, listen: &ListenConfig) -> bool {
+    let Some(encoded) = header.strip_prefix("Basic ") else {
+        return false;
+    };
+    let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(encoded.trim()) else {
+        return false;
+    };
+    let Ok(credentials) = String::from_utf8(decoded) else {
+        return false;
+    };
+
+    credentials
+        .split_once(':')
+        .is_some_and(|(user, pass)| same(user, &listen.user) & same(pass, &listen.pass))
+}
+
+// Compares the HMACs of both values in constant time, so neither the time taken
+// nor the lengths give away how much of a guess was right
+fn same(given: &str, expected: &str) -> bool {
+    let mac =
+        || Hmac::<Sha256>::new_from_slice(b"listen").expect("HMAC accepts keys of any length");
+    let mut given_mac = mac();
+    given_mac.update(given.as_bytes());
+    let mut expected_mac = mac();
+    expected_mac.update(expected.as_bytes());
+    given_mac
+        .verify_slice(&expected_mac.finalize().into_bytes())
+        .is_ok()
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+// Transport settings for the two kinds of outgoing requests: DDNS updates
+// (including OAuth2 token requests) and IP detection/connectivity checks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HttpConfig {
+    #[serde(default)]
+    pub update: TransportConfig,
+    #[serde(default)]
+    pub detection: TransportConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TransportConfig {
+    #[serde(default)]
+    pub version: HttpVersion,
+    #[serde(default = "default_tcp_nodelay")]
+    pub tcp_nodelay: bool,
+    #[serde(default)]
+    pub tcp_keepalive: Option<u64>,
+    // Detection only: connect over the family being detected, since a
+    // dual-stack host otherwise uses whichever one the OS prefers
+    #[serde(default)]
+    pub bind_family: bool,
+}
+
+fn default_tcp_nodelay() -> bool {
+    true
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            version: HttpVersion::default(),
+            tcp_nodelay: default_tcp_nodelay(),
+            tcp_keepalive: None,
+            bind_family: false,
+        }
+    }
+}
+
+// `http1` is the default because some router-embedded DDNS servers break on h2.
+// `auto` lets ALPN pick, `http2` skips negotiation and always speaks h2.
+// `http3` (experimental) speaks QUIC straight away, with no fallback to TCP.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    #[default]
+    Http1,
+    Auto,
+    Http2,
+    #[cfg(feature = "http3")]
+    Http3,
+}
+
+pub fn build_client(transport: &TransportConfig) -> reqwest::Client {
+    build(transport, None)
+}
+
+// IPv4-only and IPv6-only clients if the transport binds to the family
+pub fn build_family_clients(transport: &TransportConfig) -> Option<[reqwest::Client; 2]> {
+    transport.bind_family.then(|| {
+        [
+            build(transport, Some(Ipv4Addr::UNSPECIFIED.into())),
+            build(transport, Some(Ipv6Addr::UNSPECIFIED.into())),
+        ]
+    })
+}
+
+// Sends from this address, e.g. to check that it reaches the internet
+pub fn build_bound_client(transport: &TransportConfig, local_address: IpAddr) -> reqwest::Client {
+    build(transport, Some(local_address))
+}
+
+// A local address of one family also limits the connection to that family
+fn build(transport: &TransportConfig, local_address: Option<IpAddr>) -> reqwest::Client {
+    let builder = reqwest::Client::builder()
+        .local_address(local_address)
+        .timeout(Duration::from_secs(10))
+        .tcp_nodelay(transport.tcp_nodelay)
+        .tcp_keepalive(transport.tcp_keepalive.map(Duration::from_secs));
+
+    let builder = match transport.version {
+        HttpVersion::Http1 => builder.http1_only(),
+        HttpVersion::Auto => builder,
+        HttpVersion::Http2 => builder.http2_prior_knowledge(),
+        #[cfg(feature = "http3")]
+        HttpVersion::Http3 => builder.http3_prior_knowledge(),
+    };
+
+    builder.build().unwrap()
+}